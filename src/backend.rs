@@ -1,2 +1,6 @@
 pub mod download;
-pub mod osu;
\ No newline at end of file
+pub mod error;
+pub mod list_file;
+pub mod osu;
+#[cfg(test)]
+pub mod test_util;
@@ -1,62 +1,241 @@
-use std::{cmp::min, error::Error, path::PathBuf, sync::Arc};
+use std::{cmp::min, collections::HashSet, path::{Path, PathBuf}, sync::Arc, time::{Duration, Instant}};
 use futures_util::future::join_all;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use reqwest::Client;
+use reqwest::{header::RANGE, Client, StatusCode};
 use tokio::{fs, io::AsyncWriteExt};
-use utils::extract_filename;
-use tokio::sync::Semaphore;
+use retry::is_congestion;
+pub(crate) use retry::retry;
+use retry::retry_until;
+use utils::content_range_start;
+use validate::validate_archive;
 
 use crate::backend::download::utils::find_game_dir;
+use crate::backend::error::{Error, Result};
+use bandwidth::TokenBucket;
+use concurrency::{ConcurrencyLimiter, ConcurrencyPermit};
+use health::MirrorHealth;
+use host_limit::HostLimiter;
+use queue::{load_entries, DownloadQueue, EntryStatus};
+pub use bandwidth::BandwidthLimit;
+pub use client::{build_client, ClientConfig};
+pub use concurrency::Concurrency;
+pub use progress::{ProgressEvent, ProgressSink, SkipReason, TerminalProgress};
+pub use control::BatchControl;
+pub use queue::QueueEntry;
+pub use retry::RetryPolicy;
+pub use source::{BeatmapSource, Catboy, Nerinyan, Official, PackageVariant, Sayobot};
 
+mod bandwidth;
+mod client;
+mod concurrency;
+mod control;
+mod health;
+mod host_limit;
+mod progress;
+mod queue;
+mod retry;
+mod source;
 mod utils;
+mod validate;
 
-const SOURCE: &str = "https://txy1.sayobot.cn/beatmaps/download/mini/{id}?server=auto";
 const DOWNLOAD_DIR: &str = "./Songs";
+/// Name of the file the running batch is recorded in, inside the download directory
+const QUEUE_FILE: &str = ".download-queue.json";
+/// How often the queue file is brought up to date
+const QUEUE_SAVE_INTERVAL: Duration = Duration::from_secs(5);
+/// Minimal time between two requests to the same mirror
+const HOST_REQUEST_INTERVAL: Duration = Duration::from_millis(250);
+/// Longest pause requested by a mirror that is waited out rather than moving on to the next mirror
+const MAX_HOST_WAIT: Duration = Duration::from_secs(5);
+/// Minimal time between two byte counts reported for the same download
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+/// Partial files untouched for this long are considered abandoned
+const STALE_PART_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
 
-/// Download multiple map files concurrently
-pub async fn download_maps(map_id_lst: Vec<u32>) -> Result<String, Box<dyn Error + Send>> {
-    fs::create_dir_all(DOWNLOAD_DIR).await
-        .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
+pub struct DownloadConfig {
+    retry_policy: RetryPolicy,
+    sources: Vec<Arc<dyn BeatmapSource>>,
+    variant: PackageVariant,
+    verify_set_id: bool,
+    bandwidth: Arc<BandwidthLimit>,
+    concurrency: Concurrency,
+    control: Arc<BatchControl>,
+    progress: Vec<Arc<dyn ProgressSink>>,
+}
+
+impl DownloadConfig {
+    pub fn new() -> Self {
+        Self {
+            retry_policy: RetryPolicy::new(),
+            sources: vec![Arc::new(Sayobot)],
+            variant: PackageVariant::Mini,
+            verify_set_id: true,
+            bandwidth: Arc::new(BandwidthLimit::new()),
+            concurrency: Concurrency::Fixed(5),
+            control: Arc::new(BatchControl::new()),
+            progress: vec![Arc::new(TerminalProgress::new())],
+        }
+    }
+
+    /// Consumers of the progress events, the terminal renderer by default
+    #[inline]
+    pub fn progress(mut self, progress: Vec<Arc<dyn ProgressSink>>) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Pause and cancel switches, keep a clone around to steer the batch while it is running
+    #[inline]
+    pub fn control(mut self, control: Arc<BatchControl>) -> Self {
+        self.control = control;
+        self
+    }
+
+    #[inline]
+    pub const fn concurrency(mut self, concurrency: Concurrency) -> Self {
+        self.concurrency = concurrency;
+        self
+    }
+
+    /// Bandwidth caps, keep a clone around to adjust them while the batch is running
+    #[inline]
+    pub fn bandwidth(mut self, bandwidth: Arc<BandwidthLimit>) -> Self {
+        self.bandwidth = bandwidth;
+        self
+    }
+
+    /// Reject archives whose difficulties name a different beatmapset than the requested one
+    #[inline]
+    pub const fn verify_set_id(mut self, verify_set_id: bool) -> Self {
+        self.verify_set_id = verify_set_id;
+        self
+    }
+
+    /// Archive flavour to download, mirrors that can't serve it are skipped
+    #[inline]
+    pub const fn variant(mut self, variant: PackageVariant) -> Self {
+        self.variant = variant;
+        self
+    }
+
+    /// Mirrors to download from, in order of preference
+    ///
+    /// A map that can't be fetched from one mirror is tried on the next.
+    #[inline]
+    pub fn sources(mut self, sources: Vec<Arc<dyn BeatmapSource>>) -> Self {
+        self.sources = sources;
+        self
+    }
 
-    let map_id_lst = remove_duplicates(map_id_lst);
-    let concurrent_limit = 5;
-    let multi = Arc::new(MultiProgress::new());
-    let semaphore = Arc::new(Semaphore::new(concurrent_limit));
+    #[inline]
+    pub const fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+}
+
+/// Download multiple map files concurrently, all tasks share `client` and its connection pool
+pub async fn download_maps(client: Client, map_id_lst: Vec<u32>, config: DownloadConfig) -> Result<String> {
+    fs::create_dir_all(DOWNLOAD_DIR).await?;
+
+    let (map_id_lst, owned) = remove_duplicates(map_id_lst);
+    let retry_policy = config.retry_policy;
+    let variant = config.variant;
+    let (sources, skipped): (Vec<_>, Vec<_>) = config.sources
+        .into_iter()
+        .partition(|source| source.capabilities().supports(variant));
+    for source in skipped {
+        println!("Skipping {}, it doesn't serve {} packages.", source.name(), variant);
+    }
+    if sources.is_empty() {
+        return Err(Error::NoMirror(variant));
+    }
+    let progress = config.progress;
+    let emit = |event: ProgressEvent| progress.iter().for_each(|sink| sink.event(&event));
+    for &map_id in &owned {
+        emit(ProgressEvent::Skipped { map_id, reason: SkipReason::AlreadyOwned });
+    }
+    for &map_id in &map_id_lst {
+        emit(ProgressEvent::Queued { map_id });
+    }
+    let mirror_names: Vec<&str> = sources.iter().map(|source| source.name()).collect();
+    println!("Downloading from {}", mirror_names.join(" > "));
+    let queue = DownloadQueue::open(queue_path(), &map_id_lst).await;
+    if let Err(e) = queue.save().await {
+        eprintln!("Failed to save download queue: {}", e);
+    }
+    let batch = Arc::new(Batch {
+        client,
+        health: MirrorHealth::new(sources.len()),
+        sources,
+        variant,
+        verify_set_id: config.verify_set_id,
+        bandwidth: config.bandwidth,
+        concurrency: ConcurrencyLimiter::new(config.concurrency),
+        control: config.control,
+        hosts: HostLimiter::new(HOST_REQUEST_INTERVAL),
+        queue,
+        retry_policy,
+        progress,
+    });
     
+    let controller = {
+        let batch_clone = Arc::clone(&batch);
+        tokio::spawn(async move { batch_clone.concurrency.run_controller().await })
+    };
+    let queue_saver = {
+        let batch_clone = Arc::clone(&batch);
+        tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(QUEUE_SAVE_INTERVAL).await;
+                if let Err(e) = batch_clone.queue.save().await {
+                    eprintln!("Failed to save download queue: {}", e);
+                }
+            }
+        })
+    };
+
     let tasks: Vec<_> = map_id_lst
         .into_iter()
         .map(|map_id| {
-            let multi_clone = Arc::clone(&multi);
-            let semaphore_clone = Arc::clone(&semaphore);
+            let batch_clone = Arc::clone(&batch);
             
-            tokio::spawn(async move {
-                // Acquire semaphore permit to control concurrency
-                let _permit = semaphore_clone.acquire().await.unwrap();
-                
-                // Perform the download; errors are logged and won't interrupt other tasks
-                match download_one(map_id, multi_clone).await {
-                    Ok(()) => Ok(map_id),
-                    Err(e) => {
-                        let error_msg = e.to_string();
-                        eprintln!("Failed to download map {}: {}", map_id, error_msg);
-                        Err(map_id)
-                    }
-                }
-            })
+            // Perform the download; errors are logged and won't interrupt other tasks
+            tokio::spawn(async move { download_with_failover(map_id, &batch_clone).await })
         })
         .collect();
     
     let results = join_all(tasks).await;
+    controller.abort();
+    queue_saver.abort();
+    // Failed and cancelled maps stay in the queue so they can be resumed later
+    if let Err(e) = batch.queue.finish().await {
+        eprintln!("Failed to save download queue: {}", e);
+    }
     
     let mut success_count = 0;
     let mut fail_count = 0;
+    let mut cancel_count = 0;
+    let mut retried_count = 0;
+    // Maps served by each mirror, by mirror index
+    let mut served: Vec<Vec<u32>> = vec![Vec::new(); batch.sources.len()];
     
     for result in results {
         match result {
-            Ok(Ok(_)) => success_count += 1,
-            Ok(Err(map_id)) => {
-                eprintln!("Task failed for map {}", map_id);
-                fail_count += 1;
+            Ok(outcome) => {
+                if outcome.attempts > 1 {
+                    retried_count += 1;
+                }
+                match outcome.result {
+                    MapResult::Served(mirror) => {
+                        success_count += 1;
+                        served[mirror].push(outcome.map_id);
+                    }
+                    MapResult::Failed => {
+                        eprintln!("Task failed for map {} after {} attempt(s)", outcome.map_id, outcome.attempts);
+                        fail_count += 1;
+                    }
+                    MapResult::Cancelled => cancel_count += 1,
+                }
             }
             Err(e) => {
                 eprintln!("Task panicked: {}", e);
@@ -64,98 +243,469 @@ pub async fn download_maps(map_id_lst: Vec<u32>) -> Result<String, Box<dyn Error
             }
         }
     }
+
+    let served_by: Vec<String> = batch.sources
+        .iter()
+        .zip(served)
+        .filter(|(_, map_ids)| !map_ids.is_empty())
+        .map(|(source, map_ids)| {
+            let map_ids: Vec<String> = map_ids.iter().map(u32::to_string).collect();
+            format!("{}: {} ({})", source.name(), map_ids.len(), map_ids.join(", "))
+        })
+        .collect();
+    let mut summary = format!(
+        "{} succeeded, {} failed, {} cancelled, {} needed retries.",
+        success_count, fail_count, cancel_count, retried_count
+    );
+    if !served_by.is_empty() {
+        summary.push_str(&format!(" Served by {}.", served_by.join(", ")));
+    }
+    let (limit, lowest, highest) = batch.concurrency.limits();
+    if batch.concurrency.is_adaptive() && lowest != highest {
+        summary.push_str(&format!(
+            " Concurrency adapted between {} and {}, ended at {}.",
+            lowest, highest, limit
+        ));
+    } else {
+        summary.push_str(&format!(" Concurrency: {}.", limit));
+    }
     
-    Ok(format!("{} succeeded, {} failed.", success_count, fail_count))
-}
-
-fn remove_duplicates(map_id_lst: Vec<u32>) -> Vec<u32>{
-    if let Some(mut song_dir) = find_game_dir() {
-        song_dir.push("Songs");
-        println!("Found songs directory of Osu in {}. Removing duplicate maps...", song_dir.to_str().unwrap());
-        assert!(song_dir.is_dir());
-        let exist_map_ids: Vec<u32> = std::fs::read_dir(song_dir)
-            .unwrap()
-            .filter_map(|entry| {
-                let entry = entry.unwrap();
-                let exist_map_id: Result<u32, _> = entry.file_name()
-                    .into_string()
-                    .unwrap()
-                    .split(' ')
-                    .next()
-                    .unwrap()
-                    .parse();
-                match exist_map_id {
-                    Ok(id) => {
-                        // println!("Found duplicate map {}. Removing...", id);
-                        Some(id)
-                    },
-                    Err(_) => None
+    Ok(summary)
+}
+
+/// Settings and state shared by every download task of a batch
+struct Batch {
+    client: Client,
+    sources: Vec<Arc<dyn BeatmapSource>>,
+    health: MirrorHealth,
+    variant: PackageVariant,
+    verify_set_id: bool,
+    bandwidth: Arc<BandwidthLimit>,
+    concurrency: ConcurrencyLimiter,
+    control: Arc<BatchControl>,
+    hosts: HostLimiter,
+    queue: DownloadQueue,
+    retry_policy: RetryPolicy,
+    progress: Vec<Arc<dyn ProgressSink>>,
+}
+
+impl Batch {
+    fn emit(&self, event: ProgressEvent) {
+        for sink in &self.progress {
+            sink.event(&event);
+        }
+    }
+}
+
+/// What happened to a single map
+struct MapOutcome {
+    map_id: u32,
+    /// Attempts made across all mirrors
+    attempts: u32,
+    result: MapResult,
+}
+
+enum MapResult {
+    /// Index of the mirror that served the map
+    Served(usize),
+    /// Every mirror failed
+    Failed,
+    Cancelled,
+}
+
+/// Download a map from the first mirror in the chain that manages to serve it
+///
+/// A mirror that asked to back off for longer than `MAX_HOST_WAIT` is skipped, unless it is the last one
+/// left. Waiting for that one happens without a download slot, so other maps can use it meanwhile.
+async fn download_with_failover(map_id: u32, batch: &Batch) -> MapOutcome {
+    let mut total_attempts = 0;
+    let mut last_error = None;
+    // Acquire a download slot to control concurrency, maps cancelled while waiting never start
+    let Some(mut permit) = acquire_slot(map_id, batch).await else {
+        return cancel_map(map_id, total_attempts, batch).await;
+    };
+    let order = batch.health.order();
+    for (i, &mirror) in order.iter().enumerate() {
+        let source = batch.sources[mirror].as_ref();
+        let is_last = i + 1 == order.len();
+        let result = loop {
+            let cancelled = || batch.control.cancelled(map_id);
+            let (result, attempts) = retry_until(&batch.retry_policy, cancelled, |_| async move {
+                batch.queue.record_attempt(map_id);
+                let result = download_one(map_id, source, batch).await;
+                if let Err(e) = &result {
+                    if is_congestion(e) {
+                        batch.concurrency.record_congestion();
+                    }
                 }
+                result
             })
-            .collect();
-        let final_id_lst: Vec<u32> = map_id_lst.iter()
-            .filter(|&id| !exist_map_ids.contains(id))
-            .map(|id| *id)
-            .collect();
+            .await;
+            total_attempts += attempts;
+
+            match result {
+                Err(Error::MirrorPaused { pause, .. }) if is_last => {
+                    drop(permit);
+                    let waited = tokio::select! {
+                        _ = tokio::time::sleep(pause) => acquire_slot(map_id, batch).await,
+                        _ = batch.control.cancelled(map_id) => None,
+                    };
+                    match waited {
+                        Some(slot) => permit = slot,
+                        None => return cancel_map(map_id, total_attempts, batch).await,
+                    }
+                }
+                result => break result,
+            }
+        };
 
-        let remove_count = map_id_lst.len() - final_id_lst.len();
-        println!("Removed {} duplicate maps.", remove_count.to_string());
+        match result {
+            Ok(()) => {
+                batch.health.record_success(mirror);
+                batch.queue.set_status(map_id, EntryStatus::Done);
+                batch.emit(ProgressEvent::Finished { map_id, mirror: source.name() });
+                // Partial files from mirrors that failed earlier are of no use anymore
+                remove_parts(map_id, batch).await;
+                return MapOutcome {
+                    map_id,
+                    attempts: total_attempts,
+                    result: MapResult::Served(mirror),
+                };
+            }
+            Err(Error::Cancelled) => {
+                return cancel_map(map_id, total_attempts, batch).await;
+            }
+            Err(e) => {
+                eprintln!("Failed to download map {} from {}: {}", map_id, source.name(), e);
+                if batch.health.record_failure(mirror, &e) {
+                    eprintln!("{} keeps failing, moving it to the back of the mirror chain.", source.name());
+                }
+                last_error = Some(format!("{}: {}", source.name(), e));
+            }
+        }
+    }
 
-        final_id_lst
+    batch.queue.set_status(map_id, EntryStatus::Failed);
+    batch.emit(ProgressEvent::Failed {
+        map_id,
+        error: last_error.unwrap_or_default(),
+    });
+    MapOutcome {
+        map_id,
+        attempts: total_attempts,
+        result: MapResult::Failed,
+    }
+}
+
+/// Wait for a download slot, `None` if the map gets cancelled first
+async fn acquire_slot(map_id: u32, batch: &Batch) -> Option<ConcurrencyPermit<'_>> {
+    tokio::select! {
+        permit = batch.concurrency.acquire() => Some(permit),
+        _ = batch.control.cancelled(map_id) => None,
+    }
+}
+
+/// Drop a cancelled map together with whatever was downloaded of it
+async fn cancel_map(map_id: u32, attempts: u32, batch: &Batch) -> MapOutcome {
+    batch.queue.set_status(map_id, EntryStatus::Cancelled);
+    remove_parts(map_id, batch).await;
+    batch.emit(ProgressEvent::Skipped { map_id, reason: SkipReason::Cancelled });
+    MapOutcome {
+        map_id,
+        attempts,
+        result: MapResult::Cancelled,
+    }
+}
+
+/// Remove the partial files every mirror of the batch may have left for a map
+async fn remove_parts(map_id: u32, batch: &Batch) {
+    for source in &batch.sources {
+        let _ = fs::remove_file(part_path(map_id, batch.variant, source.as_ref())).await;
+    }
+}
+
+fn queue_path() -> PathBuf {
+    Path::new(DOWNLOAD_DIR).join(QUEUE_FILE)
+}
+
+/// Mapsets of a batch that an earlier session didn't get to finish
+///
+/// Returns `None` if there is no such batch.
+pub async fn load_unfinished_queue() -> Option<Vec<QueueEntry>> {
+    let unfinished: Vec<QueueEntry> = load_entries(&queue_path())
+        .await?
+        .into_iter()
+        .filter(|entry| entry.status != EntryStatus::Done)
+        .collect();
+    (!unfinished.is_empty()).then_some(unfinished)
+}
+
+/// Drop the record of an unfinished batch instead of resuming it
+pub async fn discard_unfinished_queue() -> std::io::Result<()> {
+    match fs::remove_file(queue_path()).await {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+/// Remove partial downloads abandoned by earlier sessions
+///
+/// Empty partial files and ones older than a week are deleted, recent ones are kept so their downloads can resume.
+/// Returns the number of removed files.
+pub fn clean_stale_parts() -> usize {
+    clean_stale_parts_in(Path::new(DOWNLOAD_DIR), STALE_PART_AGE)
+}
+
+fn clean_stale_parts_in(dir: &Path, max_age: Duration) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .filter_map(std::result::Result::ok)
+        .filter(|entry| entry.file_name().to_string_lossy().ends_with(".part"))
+        .filter(|entry| match entry.metadata() {
+            Ok(meta) => {
+                let age = meta.modified().ok().and_then(|modified| modified.elapsed().ok());
+                meta.len() == 0 || age.is_some_and(|age| age >= max_age)
+            }
+            Err(_) => false,
+        })
+        .filter(|entry| std::fs::remove_file(entry.path()).is_ok())
+        .count()
+}
+
+/// IDs of the mapsets in the osu! songs directory, `None` if the game isn't found
+pub fn owned_mapset_ids() -> Result<Option<HashSet<u32>>> {
+    let Some(mut song_dir) = find_game_dir() else {
+        return Ok(None);
+    };
+    song_dir.push("Songs");
+    let owned = std::fs::read_dir(&song_dir)
+        .map_err(|source| Error::GameDir { path: song_dir.clone(), source })?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let exist_map_id = entry.file_name()
+                .to_string_lossy()
+                .split(' ')
+                .next()?
+                .parse::<u32>();
+            exist_map_id.ok()
+        })
+        .collect();
+    Ok(Some(owned))
+}
+
+/// Split the maps into the ones to download and the ones already in the osu! songs directory
+///
+/// Skipping owned maps is a convenience, an unreadable songs directory doesn't fail the batch over it.
+fn remove_duplicates(map_id_lst: Vec<u32>) -> (Vec<u32>, Vec<u32>) {
+    let exist_map_ids = owned_mapset_ids().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        None
+    });
+    if let Some(exist_map_ids) = exist_map_ids {
+        println!("Found songs directory of Osu. Removing duplicate maps...");
+        let (final_id_lst, owned): (Vec<u32>, Vec<u32>) = map_id_lst
+            .into_iter()
+            .partition(|id| !exist_map_ids.contains(id));
+
+        println!("Removed {} duplicate maps.", owned.len());
+
+        (final_id_lst, owned)
     } else {
         println!("Can't find songs directory. Download all found maps.");
-        map_id_lst
+        (map_id_lst, Vec::new())
     }
 }
 
-async fn download_one(map_id: u32, multi: Arc<MultiProgress>) -> Result<(), Box<dyn Error + Send>> {
-    let url = SOURCE.replace("{id}", map_id.to_string().as_str());
-    let client = Client::new();
-    let mut res = client.get(&url).send().await
-        .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
-    
+/// Path of the partial file a map is streamed into before it is complete
+///
+/// Each mirror and variant gets its own file, since their archives of the same set differ.
+fn part_path(map_id: u32, variant: PackageVariant, source: &dyn BeatmapSource) -> PathBuf {
+    let mut path = PathBuf::from(DOWNLOAD_DIR);
+    path.push(format!("{}.{}.{}.osz.part", map_id, source.key(), variant.key()));
+    path
+}
+
+/// Wait for the turn of the next request to the mirror
+///
+/// Fails instead of waiting if the mirror asked to back off for longer than `MAX_HOST_WAIT`.
+async fn wait_for_host(map_id: u32, source: &dyn BeatmapSource, url: &str, batch: &Batch) -> Result<()> {
+    if let Some(pause) = batch.hosts.paused_for(url).filter(|&pause| pause > MAX_HOST_WAIT) {
+        return Err(Error::MirrorPaused { mirror: source.name(), pause });
+    }
+    tokio::select! {
+        _ = batch.hosts.wait(url) => Ok(()),
+        _ = batch.control.cancelled(map_id) => Err(Error::Cancelled),
+    }
+}
+
+async fn download_one(map_id: u32, source: &dyn BeatmapSource, batch: &Batch) -> Result<()> {
+    let variant = batch.variant;
+    let url = source.url(map_id, variant);
+    let part_path = part_path(map_id, variant, source);
+    let client = &batch.client;
+    batch.control.proceed(map_id).await?;
+
+    // Resume from a partial file left by an earlier attempt, if any
+    let mut resume_from = fs::metadata(&part_path).await
+        .map(|meta| meta.len())
+        .unwrap_or(0);
+    let mut req = source.request(client, map_id, variant);
+    if resume_from > 0 {
+        req = req.header(RANGE, format!("bytes={}-", resume_from));
+    }
+    wait_for_host(map_id, source, &url, batch).await?;
+    let mut res = req.send().await?;
+
+    // The partial file doesn't match the remote file anymore, start over
+    if res.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        resume_from = 0;
+        wait_for_host(map_id, source, &url, batch).await?;
+        res = source.request(client, map_id, variant).send().await?;
+    }
+
+    // A 206 for another range than the one asked for can't be appended, empty the partial file and start over
+    if resume_from > 0
+        && res.status() == StatusCode::PARTIAL_CONTENT
+        && content_range_start(&res) != Some(resume_from)
+    {
+        fs::File::create(&part_path).await?;
+        resume_from = 0;
+        wait_for_host(map_id, source, &url, batch).await?;
+        res = source.request(client, map_id, variant).send().await?;
+    }
+
+    // Pause every task targeting this mirror if it asks us to slow down
+    if let Some(pause) = batch.hosts.observe(&url, &res) {
+        eprintln!("{} asked to back off, pausing its downloads for {}s", source.name(), pause.as_secs());
+    }
+
+    let mut res = res.error_for_status()?;
+
+    // Mirrors that ignore `Range` answer with the whole file, so only append on a 206
+    let offset = if resume_from > 0 && res.status() == StatusCode::PARTIAL_CONTENT {
+        resume_from
+    } else {
+        0
+    };
+
     // Extract the filename
-    let filename = extract_filename(&res, &url, map_id);
+    let filename = source.filename(&res, map_id, variant);
     let mut path = PathBuf::from(DOWNLOAD_DIR);
     path.push(filename.as_str());
     
     // Get total content length
-    let total_size = res
+    let remaining_size = res
         .content_length()
-        .ok_or_else(|| format!("Failed to get content length from '{}'", url))
-        .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, e)) as Box<dyn Error + Send>)?;
-    if total_size == 0 {
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "Map has content_length of 0.")) as Box<dyn Error + Send>);
+        .ok_or_else(|| Error::InvalidResponse(format!("Failed to get content length from '{}'", url)))?;
+    if remaining_size == 0 {
+        return Err(Error::InvalidResponse("Map has content_length of 0.".to_string()));
     }
+    let total_size = offset + remaining_size;
 
-    // --- Progress indicator setup ---
-    let pb = multi.add(ProgressBar::new(total_size));
-    pb.set_style(ProgressStyle::default_bar()
-        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({percentage}%)")
-        .map_err(|e| Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, e.to_string())) as Box<dyn Error + Send>)?
-        .progress_chars("#>-"));
-    pb.set_message(format!("Downloading {}", filename));
+    batch.emit(ProgressEvent::Started {
+        map_id,
+        mirror: source.name(),
+        filename: filename.clone(),
+        downloaded: offset,
+        total: total_size,
+    });
 
     // --- Download and file writing ---
-    let mut file = fs::File::create(&path).await
-        .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
-    let mut downloaded: u64 = 0;
+    // On failure the partial file is kept for the next attempt
+    write_part(map_id, &mut res, &part_path, offset, total_size, batch).await?;
+
+    // A finished stream can still be an error page or a truncated archive
+    let expected_set_id = batch.verify_set_id.then_some(map_id);
+    let archive_path = part_path.clone();
+    let validated = tokio::task::spawn_blocking(move || validate_archive(&archive_path, expected_set_id))
+        .await
+        .map_err(std::io::Error::from)?;
+    if let Err(e) = validated {
+        let _ = fs::remove_file(&part_path).await;
+        return Err(e);
+    }
+
+    // Only a completed and validated archive gets its real name, so osu! never sees a half-written file
+    fs::rename(&part_path, &path).await?;
+
+    Ok(())
+}
+
+/// Stream the response body into the partial file, appending after `offset` bytes
+async fn write_part(
+    map_id: u32,
+    res: &mut reqwest::Response,
+    part_path: &Path,
+    offset: u64,
+    total_size: u64,
+    batch: &Batch,
+) -> Result<()> {
+    let bucket = TokenBucket::new();
+    let mut file = if offset > 0 {
+        fs::OpenOptions::new().append(true).open(&part_path).await
+    } else {
+        fs::File::create(&part_path).await
+    }?;
+    let mut downloaded: u64 = offset;
+    let mut last_report = Instant::now();
     
     loop {
-        match res.chunk().await
-            .map_err(|e| Box::new(e) as Box<dyn Error + Send>)? {
-            Some(chunk) => {
-                // Use asynchronous writes
-                file.write_all(&chunk).await
-                    .map_err(|e| Box::new(e) as Box<dyn Error + Send>)?;
-                let new = min(downloaded + (chunk.len() as u64), total_size);
-                downloaded = new;
-                pb.set_position(new);
-            }
-            None => break,
+        // While paused the stream is left alone, should the mirror drop it the next attempt resumes
+        batch.control.proceed(map_id).await?;
+        let chunk = tokio::select! {
+            chunk = res.chunk() => chunk?,
+            _ = batch.control.cancelled(map_id) => return Err(Error::Cancelled),
+        };
+        let Some(chunk) = chunk else {
+            break;
+        };
+        // Holding back here also slows down the socket once its buffers are full
+        tokio::select! {
+            _ = batch.bandwidth.consume(&bucket, chunk.len() as u64) => {}
+            _ = batch.control.cancelled(map_id) => return Err(Error::Cancelled),
+        }
+        batch.concurrency.record_bytes(chunk.len() as u64);
+        // Use asynchronous writes
+        file.write_all(&chunk).await?;
+        let new = min(downloaded + (chunk.len() as u64), total_size);
+        downloaded = new;
+        batch.queue.record_bytes(map_id, new);
+        // Chunks are small, reporting every one of them would flood the consumers
+        if last_report.elapsed() >= PROGRESS_INTERVAL {
+            last_report = Instant::now();
+            batch.emit(ProgressEvent::Bytes { map_id, downloaded: new, total: total_size });
         }
     }
-
-    pb.finish_with_message(format!("Downloaded {}", filename));
+    batch.emit(ProgressEvent::Bytes { map_id, downloaded, total: total_size });
+    file.flush().await?;
+    // Make sure the data hits the disk before the file is renamed into place
+    file.sync_all().await?;
     Ok(())
 }
+
+#[cfg(test)]
+mod test {
+    use std::{fs, time::Duration};
+
+    use crate::backend::download::*;
+    use crate::backend::test_util::temp_path;
+
+    #[test]
+    pub fn test_clean_stale_parts_keeps_finished_archives() {
+        let dir = temp_path("parts");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("1.sayobot.novideo.osz.part"), "partial").unwrap();
+        fs::write(dir.join("2.sayobot.novideo.osz.part"), "").unwrap();
+        fs::write(dir.join("3 Artist - Title.osz"), "done").unwrap();
+
+        // Recent partial files survive, empty ones don't
+        assert_eq!(clean_stale_parts_in(&dir, Duration::from_secs(3600)), 1);
+        assert!(dir.join("1.sayobot.novideo.osz.part").exists());
+
+        assert_eq!(clean_stale_parts_in(&dir, Duration::ZERO), 1);
+        assert!(dir.join("3 Artist - Title.osz").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
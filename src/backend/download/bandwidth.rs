@@ -0,0 +1,158 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use tokio::time::sleep;
+
+/// Longest single sleep of a throttled download, so rate changes apply while it waits
+const MAX_WAIT_SLICE: Duration = Duration::from_millis(250);
+
+/// Token bucket that holds at most one second worth of bytes
+///
+/// The rate is read on every check so that it can change while downloads are running.
+pub struct TokenBucket {
+    /// Available tokens (may be negative while in debt) and when they were last updated
+    state: Mutex<Option<(f64, Instant)>>,
+}
+
+impl TokenBucket {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(None),
+        }
+    }
+
+    /// Take `tokens` out of the bucket after refilling it at `rate`, returns what is left
+    fn take(&self, tokens: f64, rate: f64) -> f64 {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let left = match *state {
+            Some((left, last)) => (left + now.duration_since(last).as_secs_f64() * rate).min(rate),
+            None => rate,
+        } - tokens;
+        *state = Some((left, now));
+        left
+    }
+
+    /// Wait until `bytes` may pass at `rate` bytes per second, 0 meaning unlimited
+    ///
+    /// The bytes are reserved right away, so later callers queue up behind the debt without
+    /// waiting on each other. The lock is never held while sleeping.
+    pub async fn acquire(&self, bytes: u64, rate: &AtomicU64) {
+        let mut tokens = bytes as f64;
+        loop {
+            let current = rate.load(Ordering::Relaxed);
+            if current == 0 {
+                return;
+            }
+            let current = current as f64;
+            let left = self.take(tokens, current);
+            if left >= 0.0 {
+                return;
+            }
+            tokens = 0.0;
+            sleep(Duration::from_secs_f64(-left / current).min(MAX_WAIT_SLICE)).await;
+        }
+    }
+}
+
+/// Bandwidth caps in bytes per second, 0 meaning unlimited
+///
+/// Shared between the GUI and a running batch, changes apply to downloads already in progress.
+pub struct BandwidthLimit {
+    global: AtomicU64,
+    per_download: AtomicU64,
+    global_bucket: TokenBucket,
+}
+
+impl BandwidthLimit {
+    pub fn new() -> Self {
+        Self {
+            global: AtomicU64::new(0),
+            per_download: AtomicU64::new(0),
+            global_bucket: TokenBucket::new(),
+        }
+    }
+
+    /// Cap on the combined speed of all downloads
+    pub fn set_global(&self, bytes_per_sec: u64) {
+        self.global.store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// Cap on the speed of each single download
+    pub fn set_per_download(&self, bytes_per_sec: u64) {
+        self.per_download.store(bytes_per_sec, Ordering::Relaxed);
+    }
+
+    /// Wait until a download owning `bucket` may consume `bytes`
+    ///
+    /// Only waits, so it can be raced against the cancel signal of the download.
+    pub async fn consume(&self, bucket: &TokenBucket, bytes: u64) {
+        bucket.acquire(bytes, &self.per_download).await;
+        self.global_bucket.acquire(bytes, &self.global).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::backend::download::bandwidth::*;
+
+    #[tokio::test]
+    pub async fn test_unlimited_does_not_wait() {
+        let limit = BandwidthLimit::new();
+        let bucket = TokenBucket::new();
+        let start = Instant::now();
+        limit.consume(&bucket, 1 << 30).await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    pub async fn test_global_cap_throttles() {
+        let limit = BandwidthLimit::new();
+        limit.set_global(10_000);
+        let bucket = TokenBucket::new();
+        let start = Instant::now();
+        // The first second worth of bytes passes as a burst, the rest has to wait
+        limit.consume(&bucket, 10_000).await;
+        limit.consume(&bucket, 5_000).await;
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(400), "{:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(1500), "{:?}", elapsed);
+    }
+
+    #[tokio::test]
+    pub async fn test_lifting_the_cap_ends_the_wait() {
+        let limit = std::sync::Arc::new(BandwidthLimit::new());
+        limit.set_global(1_000);
+        let bucket = TokenBucket::new();
+        let start = Instant::now();
+        // Deep in debt, this would wait for about ten seconds at the old rate
+        limit.consume(&bucket, 1_000).await;
+        let waiting = {
+            let limit = std::sync::Arc::clone(&limit);
+            tokio::spawn(async move { limit.consume(&TokenBucket::new(), 10_000).await })
+        };
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        limit.set_global(0);
+        waiting.await.unwrap();
+        assert!(start.elapsed() < Duration::from_secs(1), "{:?}", start.elapsed());
+    }
+
+    #[tokio::test]
+    pub async fn test_per_download_cap_uses_own_bucket() {
+        let limit = BandwidthLimit::new();
+        limit.set_per_download(10_000);
+        let (first, second) = (TokenBucket::new(), TokenBucket::new());
+        let start = Instant::now();
+        // Each download gets its own burst
+        limit.consume(&first, 10_000).await;
+        limit.consume(&second, 10_000).await;
+        assert!(start.elapsed() < Duration::from_millis(200));
+    }
+}
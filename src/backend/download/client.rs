@@ -0,0 +1,60 @@
+use std::time::Duration;
+
+use reqwest::{Client, Proxy};
+
+/// User agent sent to mirrors, so they can tell who is downloading
+const USER_AGENT: &str = concat!(
+    "osu-downloader/",
+    env!("CARGO_PKG_VERSION"),
+    " (+https://github.com/nicelife1450/osu-downloader)"
+);
+
+/// Settings of the HTTP client shared by all downloads
+pub struct ClientConfig {
+    connect_timeout: Duration,
+    read_timeout: Duration,
+    proxy: Option<String>,
+}
+
+impl ClientConfig {
+    pub fn new() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(30),
+            proxy: None,
+        }
+    }
+
+    /// Proxy URL for all requests, e.g. `http://127.0.0.1:8080` or `socks5://127.0.0.1:1080`
+    ///
+    /// Without it, the usual `HTTP_PROXY`, `HTTPS_PROXY` and `ALL_PROXY` environment variables are honored.
+    #[inline]
+    pub fn proxy(mut self, proxy: String) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+}
+
+/// Build the client that every download task of the app shares
+pub fn build_client(config: &ClientConfig) -> reqwest::Result<Client> {
+    let mut builder = Client::builder()
+        .user_agent(USER_AGENT)
+        .connect_timeout(config.connect_timeout)
+        .read_timeout(config.read_timeout);
+    if let Some(proxy) = config.proxy.as_deref().filter(|proxy| !proxy.trim().is_empty()) {
+        builder = builder.proxy(Proxy::all(proxy.trim())?);
+    }
+    builder.build()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::download::client::*;
+
+    #[test]
+    pub fn test_build_client_with_proxy() {
+        assert!(build_client(&ClientConfig::new()).is_ok());
+        assert!(build_client(&ClientConfig::new().proxy("socks5://127.0.0.1:1080".to_string())).is_ok());
+        assert!(build_client(&ClientConfig::new().proxy("not a url".to_string())).is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::{
+    sync::Notify,
+    time::{interval, MissedTickBehavior},
+};
+
+/// How often the adaptive controller re-evaluates the limit
+const ADJUST_INTERVAL: Duration = Duration::from_secs(5);
+/// Throughput has to grow by this factor for another download slot to be opened
+const IMPROVEMENT_FACTOR: f64 = 1.05;
+
+/// How many maps are downloaded at the same time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Concurrency {
+    Fixed(usize),
+    /// Scale between 1 and `max` following throughput and mirror pushback
+    Adaptive { max: usize },
+}
+
+struct State {
+    limit: usize,
+    active: usize,
+    lowest: usize,
+    highest: usize,
+}
+
+/// Gate limiting the number of running downloads, its limit can change while tasks wait on it
+pub struct ConcurrencyLimiter {
+    concurrency: Concurrency,
+    state: Mutex<State>,
+    notify: Notify,
+    /// Bytes received since the last adjustment
+    bytes: AtomicU64,
+    /// A mirror pushed back (429/503/timeout) since the last adjustment
+    congested: AtomicBool,
+    /// A task had to wait for a slot since the last adjustment
+    saturated: AtomicBool,
+}
+
+/// A download slot, released on drop
+pub struct ConcurrencyPermit<'a> {
+    limiter: &'a ConcurrencyLimiter,
+}
+
+impl Drop for ConcurrencyPermit<'_> {
+    fn drop(&mut self) {
+        self.limiter.state.lock().unwrap().active -= 1;
+        self.limiter.notify.notify_waiters();
+    }
+}
+
+impl ConcurrencyLimiter {
+    pub fn new(concurrency: Concurrency) -> Self {
+        let limit = match concurrency {
+            Concurrency::Fixed(n) => n.max(1),
+            Concurrency::Adaptive { max } => max.max(1).div_ceil(2),
+        };
+        Self {
+            concurrency,
+            state: Mutex::new(State {
+                limit,
+                active: 0,
+                lowest: limit,
+                highest: limit,
+            }),
+            notify: Notify::new(),
+            bytes: AtomicU64::new(0),
+            congested: AtomicBool::new(false),
+            saturated: AtomicBool::new(false),
+        }
+    }
+
+    pub fn is_adaptive(&self) -> bool {
+        matches!(self.concurrency, Concurrency::Adaptive { .. })
+    }
+
+    /// Wait for a free download slot
+    pub async fn acquire(&self) -> ConcurrencyPermit<'_> {
+        loop {
+            // Register before checking, so a slot freed in between isn't missed
+            let notified = self.notify.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            {
+                let mut state = self.state.lock().unwrap();
+                if state.active < state.limit {
+                    state.active += 1;
+                    return ConcurrencyPermit { limiter: self };
+                }
+            }
+            self.saturated.store(true, Ordering::Relaxed);
+            notified.await;
+        }
+    }
+
+    pub fn record_bytes(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_congestion(&self) {
+        self.congested.store(true, Ordering::Relaxed);
+    }
+
+    /// Current limit together with the lowest and highest limit used so far
+    pub fn limits(&self) -> (usize, usize, usize) {
+        let state = self.state.lock().unwrap();
+        (state.limit, state.lowest, state.highest)
+    }
+
+    /// Keep adjusting the limit, runs until the task is aborted
+    pub async fn run_controller(&self) {
+        let Concurrency::Adaptive { max } = self.concurrency else {
+            return;
+        };
+        let mut ticker = interval(ADJUST_INTERVAL);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        ticker.tick().await;
+        let mut previous = None;
+        loop {
+            ticker.tick().await;
+            let throughput = self.bytes.swap(0, Ordering::Relaxed) as f64 / ADJUST_INTERVAL.as_secs_f64();
+            let congested = self.congested.swap(false, Ordering::Relaxed);
+            let saturated = self.saturated.swap(false, Ordering::Relaxed);
+
+            let mut state = self.state.lock().unwrap();
+            let limit = next_limit(state.limit, max, congested, saturated, throughput, previous);
+            if limit != state.limit {
+                println!("Adjusting concurrent downloads from {} to {}", state.limit, limit);
+                state.limit = limit;
+                state.lowest = state.lowest.min(limit);
+                state.highest = state.highest.max(limit);
+            }
+            drop(state);
+            self.notify.notify_waiters();
+            previous = Some(throughput);
+        }
+    }
+}
+
+/// Back off sharply when mirrors push back, open one more slot while it pays off
+fn next_limit(
+    limit: usize,
+    max: usize,
+    congested: bool,
+    saturated: bool,
+    throughput: f64,
+    previous: Option<f64>,
+) -> usize {
+    if congested {
+        (limit / 2).max(1)
+    } else if saturated && previous.is_none_or(|previous| throughput > previous * IMPROVEMENT_FACTOR) {
+        (limit + 1).min(max.max(1))
+    } else {
+        limit
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tokio::time::timeout;
+
+    use crate::backend::download::concurrency::*;
+
+    #[test]
+    pub fn test_next_limit() {
+        // Pushback halves the limit
+        assert_eq!(next_limit(8, 16, true, true, 1000.0, Some(10.0)), 4);
+        assert_eq!(next_limit(1, 16, true, true, 1000.0, None), 1);
+        // Growth only while all slots are busy and throughput improves
+        assert_eq!(next_limit(4, 16, false, true, 1000.0, Some(500.0)), 5);
+        assert_eq!(next_limit(4, 16, false, true, 1000.0, Some(1000.0)), 4);
+        assert_eq!(next_limit(4, 16, false, false, 1000.0, Some(500.0)), 4);
+        assert_eq!(next_limit(16, 16, false, true, 1000.0, Some(500.0)), 16);
+    }
+
+    #[tokio::test]
+    pub async fn test_acquire_waits_for_free_slot() {
+        let limiter = ConcurrencyLimiter::new(Concurrency::Fixed(1));
+        let permit = limiter.acquire().await;
+        assert!(timeout(Duration::from_millis(50), limiter.acquire()).await.is_err());
+        drop(permit);
+        assert!(timeout(Duration::from_millis(50), limiter.acquire()).await.is_ok());
+    }
+}
@@ -0,0 +1,113 @@
+use std::collections::HashSet;
+
+use tokio::sync::watch;
+
+use crate::backend::error::{Error, Result};
+
+#[derive(Default)]
+struct State {
+    paused: bool,
+    cancelled: bool,
+    cancelled_maps: HashSet<u32>,
+}
+
+impl State {
+    fn is_cancelled(&self, map_id: u32) -> bool {
+        self.cancelled || self.cancelled_maps.contains(&map_id)
+    }
+}
+
+/// Pause and cancel switches of a batch, shared between the GUI and its download tasks
+pub struct BatchControl {
+    state: watch::Sender<State>,
+}
+
+impl BatchControl {
+    pub fn new() -> Self {
+        Self {
+            state: watch::Sender::new(State::default()),
+        }
+    }
+
+    /// Hold every download at its next chunk until `resume` is called
+    pub fn pause(&self) {
+        self.state.send_modify(|state| state.paused = true);
+    }
+
+    pub fn resume(&self) {
+        self.state.send_modify(|state| state.paused = false);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.state.borrow().paused
+    }
+
+    /// Stop the whole batch, maps that didn't finish yet are dropped
+    pub fn cancel(&self) {
+        self.state.send_modify(|state| state.cancelled = true);
+    }
+
+    /// Stop a single map, the rest of the batch goes on
+    pub fn cancel_map(&self, map_id: u32) {
+        self.state.send_modify(|state| {
+            state.cancelled_maps.insert(map_id);
+        });
+    }
+
+    /// Wait while the batch is paused, fails once `map_id` is cancelled
+    pub async fn proceed(&self, map_id: u32) -> Result<()> {
+        let mut rx = self.state.subscribe();
+        let cancelled = rx
+            .wait_for(|state| !state.paused || state.is_cancelled(map_id))
+            .await
+            .is_ok_and(|state| state.is_cancelled(map_id));
+        if cancelled {
+            Err(Error::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Resolve once `map_id` is cancelled
+    pub async fn cancelled(&self, map_id: u32) {
+        let mut rx = self.state.subscribe();
+        let _ = rx.wait_for(|state| state.is_cancelled(map_id)).await;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{sync::Arc, time::Duration};
+
+    use tokio::time::timeout;
+
+    use crate::backend::download::control::*;
+
+    #[tokio::test]
+    pub async fn test_pause_holds_until_resumed() {
+        let control = Arc::new(BatchControl::new());
+        assert!(control.proceed(1).await.is_ok());
+
+        control.pause();
+        assert!(timeout(Duration::from_millis(50), control.proceed(1)).await.is_err());
+        let waiting = {
+            let control = Arc::clone(&control);
+            tokio::spawn(async move { control.proceed(1).await })
+        };
+        control.resume();
+        assert!(waiting.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    pub async fn test_cancel_releases_paused_maps() {
+        let control = BatchControl::new();
+        control.pause();
+        control.cancel_map(2);
+        assert!(control.proceed(2).await.is_err());
+        assert!(timeout(Duration::from_millis(50), control.cancelled(1)).await.is_err());
+
+        control.cancel();
+        assert!(control.proceed(1).await.is_err());
+        assert!(timeout(Duration::from_millis(50), control.cancelled(1)).await.is_ok());
+    }
+}
@@ -0,0 +1,121 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::backend::{download::retry::is_retryable, error::Error};
+
+/// Consecutive failures after which a mirror is demoted
+const FAILURE_THRESHOLD: u32 = 3;
+/// How long a demoted mirror stays at the back of the chain
+const DEMOTION_PERIOD: Duration = Duration::from_secs(120);
+
+#[derive(Default)]
+struct Health {
+    consecutive_failures: u32,
+    demoted_until: Option<Instant>,
+}
+
+/// Failure bookkeeping for an ordered chain of mirrors, shared by all download tasks
+pub struct MirrorHealth {
+    mirrors: Mutex<Vec<Health>>,
+}
+
+impl MirrorHealth {
+    pub fn new(mirror_count: usize) -> Self {
+        Self {
+            mirrors: Mutex::new((0..mirror_count).map(|_| Health::default()).collect()),
+        }
+    }
+
+    /// Mirror indices in the order they should be tried
+    ///
+    /// Healthy mirrors keep their configured order, demoted ones are moved to the end.
+    pub fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let mirrors = self.mirrors.lock().unwrap();
+        let (mut healthy, demoted): (Vec<usize>, Vec<usize>) = (0..mirrors.len())
+            .partition(|&i| mirrors[i].demoted_until.is_none_or(|until| until <= now));
+        healthy.extend(demoted);
+        healthy
+    }
+
+    pub fn record_success(&self, mirror: usize) {
+        let mut mirrors = self.mirrors.lock().unwrap();
+        mirrors[mirror] = Health::default();
+    }
+
+    /// Returns `true` if this failure got the mirror demoted
+    ///
+    /// Only transient failures and broken archives count, a map the mirror doesn't host says nothing
+    /// about its health. Error pages served with status 200 end up as broken archives.
+    pub fn record_failure(&self, mirror: usize, err: &Error) -> bool {
+        if !is_retryable(err) && !matches!(err, Error::InvalidArchive(_)) {
+            return false;
+        }
+        let mut mirrors = self.mirrors.lock().unwrap();
+        let health = &mut mirrors[mirror];
+        health.consecutive_failures += 1;
+        if health.consecutive_failures >= FAILURE_THRESHOLD {
+            health.consecutive_failures = 0;
+            health.demoted_until = Some(Instant::now() + DEMOTION_PERIOD);
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use reqwest::StatusCode;
+
+    use crate::backend::download::health::*;
+
+    fn http_error(status: StatusCode) -> Error {
+        Error::Http { status, url: String::new() }
+    }
+
+    #[test]
+    pub fn test_failing_mirror_is_demoted() {
+        let health = MirrorHealth::new(3);
+        assert_eq!(health.order(), vec![0, 1, 2]);
+
+        let unavailable = http_error(StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!health.record_failure(0, &unavailable));
+        assert!(!health.record_failure(0, &unavailable));
+        assert!(health.record_failure(0, &unavailable));
+        assert_eq!(health.order(), vec![1, 2, 0]);
+    }
+
+    #[test]
+    pub fn test_missing_maps_dont_demote() {
+        let health = MirrorHealth::new(2);
+        let not_found = http_error(StatusCode::NOT_FOUND);
+        for _ in 0..5 {
+            assert!(!health.record_failure(0, &not_found));
+        }
+        assert_eq!(health.order(), vec![0, 1]);
+    }
+
+    #[test]
+    pub fn test_broken_archives_demote() {
+        let health = MirrorHealth::new(2);
+        let broken = Error::InvalidArchive("not a zip archive".to_string());
+        assert!(!health.record_failure(0, &broken));
+        assert!(!health.record_failure(0, &broken));
+        assert!(health.record_failure(0, &broken));
+        assert_eq!(health.order(), vec![1, 0]);
+    }
+
+    #[test]
+    pub fn test_success_resets_failures() {
+        let health = MirrorHealth::new(2);
+        let unavailable = http_error(StatusCode::SERVICE_UNAVAILABLE);
+        health.record_failure(1, &unavailable);
+        health.record_failure(1, &unavailable);
+        health.record_success(1);
+        assert!(!health.record_failure(1, &unavailable));
+        assert_eq!(health.order(), vec![0, 1]);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant, SystemTime},
+};
+
+use reqwest::{header::RETRY_AFTER, Response, StatusCode, Url};
+use tokio::time::sleep_until;
+
+/// Pause applied after a 429 that doesn't say how long to wait
+const DEFAULT_PAUSE: Duration = Duration::from_secs(30);
+/// Upper bound for pauses requested by a mirror
+const MAX_PAUSE: Duration = Duration::from_secs(15 * 60);
+
+#[derive(Default)]
+struct HostState {
+    /// Earliest time the next request may start
+    next_request: Option<Instant>,
+    /// Set while the host asked us to back off
+    paused_until: Option<Instant>,
+}
+
+/// Per-host request pacing shared by all download tasks
+///
+/// Requests to one host are spaced by a minimal interval, and a 429 or 503 response pauses
+/// every task targeting that host for as long as its `Retry-After` header asks.
+pub struct HostLimiter {
+    min_interval: Duration,
+    hosts: Mutex<HashMap<String, HostState>>,
+}
+
+impl HostLimiter {
+    pub fn new(min_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            hosts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Wait until a request to the host of `url` is allowed and reserve its slot
+    pub async fn wait(&self, url: &str) {
+        let Some(host) = host_of(url) else {
+            return;
+        };
+        let start = {
+            let mut hosts = self.hosts.lock().unwrap();
+            let state = hosts.entry(host).or_default();
+            let now = Instant::now();
+            let start = [Some(now), state.next_request, state.paused_until]
+                .into_iter()
+                .flatten()
+                .max()
+                .unwrap_or(now);
+            state.next_request = Some(start + self.min_interval);
+            start
+        };
+        sleep_until(start.into()).await;
+    }
+
+    /// Time left until the host of `url` lifts the pause it asked for, if it is paused
+    pub fn paused_for(&self, url: &str) -> Option<Duration> {
+        let host = host_of(url)?;
+        let hosts = self.hosts.lock().unwrap();
+        let until = hosts.get(&host)?.paused_until?;
+        until.checked_duration_since(Instant::now()).filter(|left| !left.is_zero())
+    }
+
+    /// Look at a response for signs that the host wants us to slow down
+    ///
+    /// Returns the pause that was applied, if any.
+    pub fn observe(&self, url: &str, res: &Response) -> Option<Duration> {
+        let status = res.status();
+        if status != StatusCode::TOO_MANY_REQUESTS && status != StatusCode::SERVICE_UNAVAILABLE {
+            return None;
+        }
+        let retry_after = res
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+        // A 503 without `Retry-After` is just an error, the retry backoff deals with it
+        let pause = match (status, retry_after) {
+            (_, Some(pause)) => pause.min(MAX_PAUSE),
+            (StatusCode::TOO_MANY_REQUESTS, None) => DEFAULT_PAUSE,
+            _ => return None,
+        };
+        let host = host_of(url)?;
+        let mut hosts = self.hosts.lock().unwrap();
+        let state = hosts.entry(host).or_default();
+        let until = Instant::now() + pause;
+        state.paused_until = Some(state.paused_until.map_or(until, |current| current.max(until)));
+        Some(pause)
+    }
+}
+
+fn host_of(url: &str) -> Option<String> {
+    Url::parse(url).ok()?.host_str().map(str::to_owned)
+}
+
+/// Parse a `Retry-After` value, either delay seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let date = httpdate::parse_http_date(value).ok()?;
+    Some(date.duration_since(SystemTime::now()).unwrap_or(Duration::ZERO))
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant, SystemTime};
+
+    use crate::backend::download::host_limit::*;
+
+    #[test]
+    pub fn test_parse_retry_after() {
+        assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), Some(Duration::ZERO));
+        let later = httpdate::fmt_http_date(SystemTime::now() + Duration::from_secs(60));
+        let parsed = parse_retry_after(&later).unwrap();
+        assert!(parsed > Duration::from_secs(50) && parsed <= Duration::from_secs(60));
+        assert_eq!(parse_retry_after("soon"), None);
+    }
+
+    #[tokio::test]
+    pub async fn test_requests_to_same_host_are_spaced() {
+        let limiter = HostLimiter::new(Duration::from_millis(100));
+        let start = Instant::now();
+        limiter.wait("https://a.example/d/1").await;
+        limiter.wait("https://b.example/d/1").await;
+        assert!(start.elapsed() < Duration::from_millis(50));
+        limiter.wait("https://a.example/d/2").await;
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    pub async fn test_paused_for_reports_remaining_pause() {
+        let limiter = HostLimiter::new(Duration::ZERO);
+        assert_eq!(limiter.paused_for("https://a.example/d/1"), None);
+        limiter.hosts.lock().unwrap().entry("a.example".to_string()).or_default().paused_until =
+            Some(Instant::now() + Duration::from_secs(60));
+        let left = limiter.paused_for("https://a.example/d/2").unwrap();
+        assert!(left > Duration::from_secs(50) && left <= Duration::from_secs(60));
+        assert_eq!(limiter.paused_for("https://b.example/d/1"), None);
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashMap, fmt, sync::Mutex};
+
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use tokio::sync::mpsc::UnboundedSender;
+
+/// Why a map of the batch wasn't downloaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SkipReason {
+    /// The set is already in the osu! songs directory
+    AlreadyOwned,
+    Cancelled,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::AlreadyOwned => write!(f, "already owned"),
+            SkipReason::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+/// What happens to the maps of a batch, in the order it happens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProgressEvent {
+    Queued { map_id: u32 },
+    /// An attempt got a response and starts streaming, `downloaded` is non-zero when it resumes
+    Started {
+        map_id: u32,
+        mirror: &'static str,
+        filename: String,
+        downloaded: u64,
+        total: u64,
+    },
+    Bytes { map_id: u32, downloaded: u64, total: u64 },
+    Finished { map_id: u32, mirror: &'static str },
+    /// Every mirror gave up on the map
+    Failed { map_id: u32, error: String },
+    Skipped { map_id: u32, reason: SkipReason },
+}
+
+/// Consumer of the progress events of a batch
+pub trait ProgressSink: Send + Sync {
+    fn event(&self, event: &ProgressEvent);
+}
+
+/// Forward events to a channel, e.g. for the GUI to pick them up
+impl ProgressSink for UnboundedSender<ProgressEvent> {
+    fn event(&self, event: &ProgressEvent) {
+        // Nobody listening anymore is fine, the batch goes on
+        let _ = self.send(event.clone());
+    }
+}
+
+/// Render progress bars in the terminal
+pub struct TerminalProgress {
+    multi: MultiProgress,
+    bars: Mutex<HashMap<u32, ProgressBar>>,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        Self {
+            multi: MultiProgress::new(),
+            bars: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ProgressSink for TerminalProgress {
+    fn event(&self, event: &ProgressEvent) {
+        let mut bars = self.bars.lock().unwrap();
+        match event {
+            ProgressEvent::Started {
+                map_id,
+                filename,
+                downloaded,
+                total,
+                ..
+            } => {
+                // A retry reuses the bar of the attempt before it
+                let pb = bars
+                    .entry(*map_id)
+                    .or_insert_with(|| self.multi.add(ProgressBar::new(*total)));
+                pb.reset();
+                pb.set_length(*total);
+                pb.set_style(
+                    ProgressStyle::default_bar()
+                        .template("{msg}\n{spinner:.green} [{elapsed_precise}] [{wide_bar:.cyan/blue}] {bytes}/{total_bytes} ({percentage}%)")
+                        .unwrap()
+                        .progress_chars("#>-"),
+                );
+                if *downloaded > 0 {
+                    pb.set_message(format!("Resuming {}", filename));
+                } else {
+                    pb.set_message(format!("Downloading {}", filename));
+                }
+                pb.set_position(*downloaded);
+            }
+            ProgressEvent::Bytes { map_id, downloaded, .. } => {
+                if let Some(pb) = bars.get(map_id) {
+                    pb.set_position(*downloaded);
+                }
+            }
+            ProgressEvent::Finished { map_id, mirror } => {
+                if let Some(pb) = bars.remove(map_id) {
+                    pb.finish_with_message(format!("Map {} served by {}", map_id, mirror));
+                }
+            }
+            ProgressEvent::Failed { map_id, error } => {
+                if let Some(pb) = bars.remove(map_id) {
+                    pb.abandon_with_message(format!("Map {} failed: {}", map_id, error));
+                }
+            }
+            ProgressEvent::Skipped {
+                map_id,
+                reason: SkipReason::Cancelled,
+            } => {
+                if let Some(pb) = bars.remove(map_id) {
+                    pb.abandon_with_message(format!("Map {} cancelled", map_id));
+                }
+            }
+            ProgressEvent::Queued { .. } | ProgressEvent::Skipped { .. } => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::sync::mpsc::unbounded_channel;
+
+    use crate::backend::download::progress::*;
+
+    #[test]
+    pub fn test_channel_sink_forwards_events() {
+        let (tx, mut rx) = unbounded_channel();
+        let sink: &dyn ProgressSink = &tx;
+        sink.event(&ProgressEvent::Queued { map_id: 1 });
+        sink.event(&ProgressEvent::Skipped {
+            map_id: 2,
+            reason: SkipReason::AlreadyOwned,
+        });
+        assert_eq!(rx.try_recv().unwrap(), ProgressEvent::Queued { map_id: 1 });
+        assert!(matches!(rx.try_recv().unwrap(), ProgressEvent::Skipped { map_id: 2, .. }));
+
+        // A receiver that went away doesn't disturb the batch
+        drop(rx);
+        sink.event(&ProgressEvent::Queued { map_id: 3 });
+    }
+}
@@ -0,0 +1,212 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EntryStatus {
+    Pending,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+/// A mapset of a batch and how far its download got
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub mapset_id: u32,
+    pub status: EntryStatus,
+    pub attempts: u32,
+    pub bytes_done: u64,
+}
+
+impl QueueEntry {
+    fn new(mapset_id: u32) -> Self {
+        Self {
+            mapset_id,
+            status: EntryStatus::Pending,
+            attempts: 0,
+            bytes_done: 0,
+        }
+    }
+}
+
+struct State {
+    entries: Vec<QueueEntry>,
+    index: HashMap<u32, usize>,
+    dirty: bool,
+}
+
+/// The mapsets of the running batch, mirrored to disk so an interrupted batch can be resumed
+pub struct DownloadQueue {
+    path: PathBuf,
+    state: Mutex<State>,
+}
+
+impl DownloadQueue {
+    /// Start a queue for `mapset_ids`, keeping progress recorded for them by an earlier session
+    pub async fn open(path: PathBuf, mapset_ids: &[u32]) -> Self {
+        let mut previous: HashMap<u32, QueueEntry> = load_entries(&path)
+            .await
+            .unwrap_or_default()
+            .into_iter()
+            .map(|entry| (entry.mapset_id, entry))
+            .collect();
+        let entries: Vec<QueueEntry> = mapset_ids
+            .iter()
+            .map(|&id| match previous.remove(&id) {
+                Some(entry) => QueueEntry {
+                    status: EntryStatus::Pending,
+                    ..entry
+                },
+                None => QueueEntry::new(id),
+            })
+            .collect();
+        let index = entries
+            .iter()
+            .enumerate()
+            .map(|(i, entry)| (entry.mapset_id, i))
+            .collect();
+        Self {
+            path,
+            state: Mutex::new(State {
+                entries,
+                index,
+                dirty: true,
+            }),
+        }
+    }
+
+    fn update(&self, mapset_id: u32, f: impl FnOnce(&mut QueueEntry)) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(&i) = state.index.get(&mapset_id) {
+            f(&mut state.entries[i]);
+            state.dirty = true;
+        }
+    }
+
+    pub fn record_attempt(&self, mapset_id: u32) {
+        self.update(mapset_id, |entry| entry.attempts += 1);
+    }
+
+    pub fn record_bytes(&self, mapset_id: u32, bytes_done: u64) {
+        self.update(mapset_id, |entry| entry.bytes_done = bytes_done);
+    }
+
+    pub fn set_status(&self, mapset_id: u32, status: EntryStatus) {
+        self.update(mapset_id, |entry| entry.status = status);
+    }
+
+    /// Write the queue to disk if anything changed since the last save
+    pub async fn save(&self) -> io::Result<()> {
+        let json = {
+            let mut state = self.state.lock().unwrap();
+            if !state.dirty {
+                return Ok(());
+            }
+            state.dirty = false;
+            serde_json::to_vec_pretty(&state.entries)?
+        };
+        // Write next to the queue file and rename, so a crash never leaves it half-written
+        let tmp_path = self.path.with_extension("json.tmp");
+        fs::write(&tmp_path, json).await?;
+        fs::rename(&tmp_path, &self.path).await
+    }
+
+    /// Keep only the entries left to do once the batch has run to the end
+    ///
+    /// The queue file is removed if every mapset got downloaded.
+    pub async fn finish(&self) -> io::Result<()> {
+        let all_done = {
+            let mut state = self.state.lock().unwrap();
+            state.entries.retain(|entry| entry.status != EntryStatus::Done);
+            state.index = state
+                .entries
+                .iter()
+                .enumerate()
+                .map(|(i, entry)| (entry.mapset_id, i))
+                .collect();
+            state.dirty = true;
+            state.entries.is_empty()
+        };
+        if all_done {
+            self.remove().await
+        } else {
+            self.save().await
+        }
+    }
+
+    /// Forget the queue once its batch has run to the end
+    pub async fn remove(&self) -> io::Result<()> {
+        match fs::remove_file(&self.path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Entries of a queue file, `None` if there is none or it can't be read
+pub async fn load_entries(path: &Path) -> Option<Vec<QueueEntry>> {
+    let json = fs::read(path).await.ok()?;
+    serde_json::from_slice(&json).ok()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::download::queue::*;
+    use crate::backend::test_util::temp_path;
+
+    #[tokio::test]
+    pub async fn test_queue_survives_reopen() {
+        let path = temp_path("queue.json");
+        let queue = DownloadQueue::open(path.clone(), &[1, 2, 3]).await;
+        queue.record_attempt(1);
+        queue.record_bytes(1, 512);
+        queue.set_status(2, EntryStatus::Done);
+        queue.save().await.unwrap();
+
+        let entries = load_entries(&path).await.unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[1].status, EntryStatus::Done);
+
+        // Progress of mapsets that are queued again is kept
+        let queue = DownloadQueue::open(path.clone(), &[1, 4]).await;
+        queue.save().await.unwrap();
+        let entries = load_entries(&path).await.unwrap();
+        assert_eq!(
+            entries[0],
+            QueueEntry {
+                mapset_id: 1,
+                status: EntryStatus::Pending,
+                attempts: 1,
+                bytes_done: 512,
+            }
+        );
+        assert_eq!(entries[1], QueueEntry::new(4));
+
+        queue.remove().await.unwrap();
+        assert!(load_entries(&path).await.is_none());
+    }
+
+    #[tokio::test]
+    pub async fn test_finish_keeps_unfinished_entries() {
+        let path = temp_path("queue-finish.json");
+        let queue = DownloadQueue::open(path.clone(), &[1, 2, 3]).await;
+        queue.set_status(1, EntryStatus::Done);
+        queue.set_status(2, EntryStatus::Failed);
+        queue.set_status(3, EntryStatus::Cancelled);
+        queue.finish().await.unwrap();
+        let ids: Vec<u32> = load_entries(&path).await.unwrap().iter().map(|entry| entry.mapset_id).collect();
+        assert_eq!(ids, vec![2, 3]);
+
+        let queue = DownloadQueue::open(path.clone(), &[2]).await;
+        queue.set_status(2, EntryStatus::Done);
+        queue.finish().await.unwrap();
+        assert!(load_entries(&path).await.is_none());
+    }
+}
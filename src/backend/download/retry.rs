@@ -0,0 +1,265 @@
+use std::{
+    future::{pending, Future},
+    hash::{BuildHasher, Hasher, RandomState},
+    io::ErrorKind,
+    time::Duration,
+};
+
+use reqwest::StatusCode;
+use rosu_v2::error::OsuError;
+use tokio::time::sleep;
+
+use crate::backend::error::{Error, Result};
+
+/// How often and how patiently a failed download is retried
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    base_delay: Duration,
+    max_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Total number of attempts, including the first one
+    #[inline]
+    pub const fn max_attempts(mut self, max_attempts: u32) -> Self {
+        self.max_attempts = max_attempts;
+        self
+    }
+
+    /// Delay before the attempt following `attempt`: exponential backoff with jitter
+    ///
+    /// The result lies between half and all of `base_delay * 2^(attempt - 1)`, capped at `max_delay`.
+    pub fn delay_after(&self, attempt: u32) -> Duration {
+        let exp = attempt.saturating_sub(1).min(16);
+        let delay = self.base_delay.saturating_mul(1 << exp).min(self.max_delay);
+        let jitter = RandomState::new().build_hasher().finish() % 1000;
+        delay / 2 + (delay / 2).mul_f64(jitter as f64 / 1000.0)
+    }
+}
+
+/// Whether an HTTP status is worth another try
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::REQUEST_TIMEOUT
+            | StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Tell transient failures (timeouts, dropped connections, 429/5xx) from fatal ones
+pub fn is_retryable(err: &Error) -> bool {
+    match err {
+        Error::Network(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body() || e.is_decode(),
+        Error::Http { status, .. } => is_retryable_status(*status),
+        Error::Search(e) => is_transient_api_error(e),
+        Error::Io(e) => matches!(
+            e.kind(),
+            ErrorKind::TimedOut
+                | ErrorKind::Interrupted
+                | ErrorKind::UnexpectedEof
+                | ErrorKind::ConnectionReset
+                | ErrorKind::ConnectionAborted
+        ),
+        _ => false,
+    }
+}
+
+/// Whether an osu! API failure may go away on its own: timeouts, dropped connections, 429/5xx
+///
+/// An expired token is refreshed by the client, so a failed refresh is worth another try as well.
+fn is_transient_api_error(err: &OsuError) -> bool {
+    match err {
+        OsuError::Request { .. }
+        | OsuError::RequestTimeout
+        | OsuError::ServiceUnavailable { .. }
+        | OsuError::ChunkingResponse { .. }
+        | OsuError::UpdateToken { .. } => true,
+        OsuError::Response { status, .. } => {
+            StatusCode::from_u16(status.as_u16()).is_ok_and(is_retryable_status)
+        }
+        _ => false,
+    }
+}
+
+/// Whether a failure means the mirror is overloaded: 429/503 responses and timeouts
+pub fn is_congestion(err: &Error) -> bool {
+    match err {
+        Error::Network(e) => e.is_timeout(),
+        Error::Http { status, .. } => {
+            *status == StatusCode::TOO_MANY_REQUESTS || *status == StatusCode::SERVICE_UNAVAILABLE
+        }
+        Error::Io(e) => e.kind() == ErrorKind::TimedOut,
+        _ => false,
+    }
+}
+
+/// Run `op` until it succeeds, fails with a fatal error or runs out of attempts
+///
+/// `op` receives the 1-based attempt number. Returns the last result together with the number of attempts made.
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, op: F) -> (Result<T>, u32)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    retry_until(policy, pending::<()>, op).await
+}
+
+/// Like [`retry`], but a backoff ends with `Error::Cancelled` as soon as the future made by `cancelled` resolves
+pub async fn retry_until<T, F, Fut, C, CFut>(policy: &RetryPolicy, mut cancelled: C, mut op: F) -> (Result<T>, u32)
+where
+    F: FnMut(u32) -> Fut,
+    Fut: Future<Output = Result<T>>,
+    C: FnMut() -> CFut,
+    CFut: Future<Output = ()>,
+{
+    let mut attempt = 1;
+    loop {
+        match op(attempt).await {
+            Ok(value) => return (Ok(value), attempt),
+            Err(e) if attempt < policy.max_attempts && is_retryable(&e) => {
+                let delay = policy.delay_after(attempt);
+                eprintln!(
+                    "Attempt {}/{} failed: {}. Retrying in {:.1}s...",
+                    attempt,
+                    policy.max_attempts,
+                    e,
+                    delay.as_secs_f32()
+                );
+                tokio::select! {
+                    _ = sleep(delay) => {}
+                    _ = cancelled() => return (Err(Error::Cancelled), attempt),
+                }
+                attempt += 1;
+            }
+            Err(e) => return (Err(e), attempt),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use reqwest::Client;
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use crate::backend::download::retry::*;
+
+    /// Serve one canned response per connection, in order, and return the server URL
+    async fn mock_server(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 1024];
+                let _ = socket.read(&mut buf).await;
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    fn response(status: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            body.len(),
+            body
+        )
+    }
+
+    fn fast_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+        }
+    }
+
+    async fn fetch(client: &Client, url: &str) -> Result<String> {
+        let res = client.get(url).send().await?.error_for_status()?;
+        Ok(res.text().await?)
+    }
+
+    #[tokio::test]
+    pub async fn test_retry_recovers_from_server_errors() {
+        let url = mock_server(vec![
+            response("503 Service Unavailable", ""),
+            response("429 Too Many Requests", ""),
+            response("200 OK", "osz"),
+        ])
+        .await;
+        let client = Client::new();
+        let (result, attempts) = retry(&fast_policy(), |_| fetch(&client, &url)).await;
+        assert_eq!(result.unwrap(), "osz");
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    pub async fn test_retry_stops_on_fatal_status() {
+        let url = mock_server(vec![response("404 Not Found", ""), response("200 OK", "osz")]).await;
+        let client = Client::new();
+        let (result, attempts) = retry(&fast_policy(), |_| fetch(&client, &url)).await;
+        assert!(matches!(result, Err(Error::Http { status: StatusCode::NOT_FOUND, .. })));
+        assert_eq!(attempts, 1);
+    }
+
+    #[tokio::test]
+    pub async fn test_retry_gives_up_after_max_attempts() {
+        let url = mock_server(vec![response("502 Bad Gateway", ""); 3]).await;
+        let client = Client::new();
+        let (result, attempts) = retry(&fast_policy(), |_| fetch(&client, &url)).await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 3);
+    }
+
+    #[tokio::test]
+    pub async fn test_retry_until_stops_backoff_on_cancel() {
+        let url = mock_server(vec![response("503 Service Unavailable", "")]).await;
+        let client = Client::new();
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_secs(30),
+            max_delay: Duration::from_secs(30),
+        };
+        let (result, attempts) = retry_until(&policy, || async {}, |_| fetch(&client, &url)).await;
+        assert!(matches!(result, Err(Error::Cancelled)));
+        assert_eq!(attempts, 1);
+    }
+
+    #[test]
+    pub fn test_transient_search_errors_are_retryable() {
+        assert!(is_retryable(&Error::Search(OsuError::RequestTimeout)));
+        assert!(!is_retryable(&Error::Search(OsuError::NotFound)));
+        assert!(!is_retryable(&Error::Auth(OsuError::RequestTimeout)));
+    }
+
+    #[test]
+    pub fn test_delay_after_is_capped() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(4),
+        };
+        assert!(policy.delay_after(1) <= Duration::from_secs(1));
+        assert!(policy.delay_after(1) >= Duration::from_millis(500));
+        assert!(policy.delay_after(10) <= Duration::from_secs(4));
+    }
+}
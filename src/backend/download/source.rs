@@ -0,0 +1,268 @@
+use std::env;
+
+use reqwest::{
+    header::{COOKIE, REFERER},
+    Client, RequestBuilder, Response,
+};
+
+use super::utils::extract_filename;
+
+/// Flavour of a beatmapset archive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackageVariant {
+    /// Everything, including the background video
+    Full,
+    /// Everything but the video
+    NoVideo,
+    /// Video and storyboard assets stripped, smallest download
+    Mini,
+}
+
+impl PackageVariant {
+    pub const ALL: &'static [PackageVariant] = &[
+        PackageVariant::Full,
+        PackageVariant::NoVideo,
+        PackageVariant::Mini,
+    ];
+
+    /// Short identifier used in file names
+    pub const fn key(self) -> &'static str {
+        match self {
+            PackageVariant::Full => "full",
+            PackageVariant::NoVideo => "novideo",
+            PackageVariant::Mini => "mini",
+        }
+    }
+}
+
+impl std::fmt::Display for PackageVariant {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackageVariant::Full => write!(f, "Full (with video)"),
+            PackageVariant::NoVideo => write!(f, "No video"),
+            PackageVariant::Mini => write!(f, "Mini (no video, no storyboard)"),
+        }
+    }
+}
+
+/// Which archive flavours a mirror is able to serve
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceCapabilities {
+    /// Full archive, including the background video
+    pub video: bool,
+    /// Archive with the video stripped
+    pub no_video: bool,
+    /// Archive with video and storyboard assets stripped
+    pub mini: bool,
+}
+
+impl SourceCapabilities {
+    pub const fn supports(self, variant: PackageVariant) -> bool {
+        match variant {
+            PackageVariant::Full => self.video,
+            PackageVariant::NoVideo => self.no_video,
+            PackageVariant::Mini => self.mini,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceCapabilities {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let flavours: Vec<&str> = [(self.video, "full"), (self.no_video, "no video"), (self.mini, "mini")]
+            .into_iter()
+            .filter_map(|(supported, name)| supported.then_some(name))
+            .collect();
+        write!(f, "{}", flavours.join(", "))
+    }
+}
+
+/// A place beatmapset archives can be downloaded from
+pub trait BeatmapSource: Send + Sync {
+    /// Human readable name of the mirror
+    fn name(&self) -> &'static str;
+
+    /// Short identifier used in file names
+    fn key(&self) -> &'static str;
+
+    /// Whether the mirror can be used at all, e.g. has the credentials it needs
+    fn is_available(&self) -> bool {
+        true
+    }
+
+    fn capabilities(&self) -> SourceCapabilities;
+
+    /// Download URL of a mapset, only called with variants listed in the capabilities
+    fn url(&self, mapset_id: u32, variant: PackageVariant) -> String;
+
+    /// Build the download request, sources needing extra headers override this
+    fn request(&self, client: &Client, mapset_id: u32, variant: PackageVariant) -> RequestBuilder {
+        client.get(self.url(mapset_id, variant))
+    }
+
+    /// Name the archive is saved under
+    ///
+    /// Always starts with the mapset ID so that duplicate detection recognizes it later.
+    fn filename(&self, res: &Response, mapset_id: u32, variant: PackageVariant) -> String {
+        let filename = extract_filename(res, &self.url(mapset_id, variant), mapset_id);
+        let id = mapset_id.to_string();
+        if filename.split(' ').next() == Some(id.as_str()) || filename == format!("{}.osz", id) {
+            filename
+        } else {
+            format!("{} {}", id, filename)
+        }
+    }
+}
+
+/// [Sayobot](https://osu.sayobot.cn/home), a fast mirror hosted in China
+pub struct Sayobot;
+
+impl BeatmapSource for Sayobot {
+    fn name(&self) -> &'static str {
+        "Sayobot"
+    }
+
+    fn key(&self) -> &'static str {
+        "sayobot"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            video: true,
+            no_video: true,
+            mini: true,
+        }
+    }
+
+    fn url(&self, mapset_id: u32, variant: PackageVariant) -> String {
+        format!(
+            "https://txy1.sayobot.cn/beatmaps/download/{}/{}?server=auto",
+            variant.key(),
+            mapset_id
+        )
+    }
+}
+
+/// The official osu! website
+///
+/// Downloads require a logged-in session, read from the `OSU_SESSION` environment variable
+/// (the value of the `osu_session` cookie in your browser).
+pub struct Official;
+
+impl BeatmapSource for Official {
+    fn name(&self) -> &'static str {
+        "osu! (official)"
+    }
+
+    fn key(&self) -> &'static str {
+        "official"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            video: true,
+            no_video: true,
+            mini: false,
+        }
+    }
+
+    fn url(&self, mapset_id: u32, variant: PackageVariant) -> String {
+        match variant {
+            PackageVariant::NoVideo => format!("https://osu.ppy.sh/beatmapsets/{}/download?noVideo=1", mapset_id),
+            _ => format!("https://osu.ppy.sh/beatmapsets/{}/download", mapset_id),
+        }
+    }
+
+    fn is_available(&self) -> bool {
+        env::var("OSU_SESSION").is_ok_and(|session| !session.is_empty())
+    }
+
+    fn request(&self, client: &Client, mapset_id: u32, variant: PackageVariant) -> RequestBuilder {
+        let mut req = client
+            .get(self.url(mapset_id, variant))
+            .header(REFERER, format!("https://osu.ppy.sh/beatmapsets/{}", mapset_id));
+        if let Ok(session) = env::var("OSU_SESSION") {
+            req = req.header(COOKIE, format!("osu_session={}", session));
+        }
+        req
+    }
+}
+
+/// [Nerinyan](https://nerinyan.moe), a community mirror
+pub struct Nerinyan;
+
+impl BeatmapSource for Nerinyan {
+    fn name(&self) -> &'static str {
+        "Nerinyan"
+    }
+
+    fn key(&self) -> &'static str {
+        "nerinyan"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            video: true,
+            no_video: true,
+            mini: false,
+        }
+    }
+
+    fn url(&self, mapset_id: u32, variant: PackageVariant) -> String {
+        match variant {
+            PackageVariant::NoVideo => format!("https://api.nerinyan.moe/d/{}?noVideo=true", mapset_id),
+            _ => format!("https://api.nerinyan.moe/d/{}", mapset_id),
+        }
+    }
+}
+
+/// [Mino](https://catboy.best), a community mirror
+pub struct Catboy;
+
+impl BeatmapSource for Catboy {
+    fn name(&self) -> &'static str {
+        "Mino (catboy.best)"
+    }
+
+    fn key(&self) -> &'static str {
+        "catboy"
+    }
+
+    fn capabilities(&self) -> SourceCapabilities {
+        SourceCapabilities {
+            video: true,
+            no_video: true,
+            mini: false,
+        }
+    }
+
+    fn url(&self, mapset_id: u32, variant: PackageVariant) -> String {
+        match variant {
+            PackageVariant::NoVideo => format!("https://catboy.best/d/{}n", mapset_id),
+            _ => format!("https://catboy.best/d/{}", mapset_id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::download::source::*;
+
+    #[test]
+    pub fn test_sayobot_url_follows_variant() {
+        assert_eq!(
+            Sayobot.url(1, PackageVariant::Full),
+            "https://txy1.sayobot.cn/beatmaps/download/full/1?server=auto"
+        );
+        assert_eq!(
+            Sayobot.url(1, PackageVariant::Mini),
+            "https://txy1.sayobot.cn/beatmaps/download/mini/1?server=auto"
+        );
+    }
+
+    #[test]
+    pub fn test_capabilities_support_variant() {
+        assert!(Sayobot.capabilities().supports(PackageVariant::Mini));
+        assert!(!Nerinyan.capabilities().supports(PackageVariant::Mini));
+        assert_eq!(Catboy.capabilities().to_string(), "full, no video");
+    }
+}
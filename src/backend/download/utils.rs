@@ -167,6 +167,21 @@ pub fn extract_filename(res: &reqwest::Response, url: &str, map_id: u32) -> Stri
     decoded
 }
 
+// Extract the first byte position of a partial response from its `content-range` header
+pub fn content_range_start(res: &reqwest::Response) -> Option<u64> {
+    res.headers()
+        .get("content-range")
+        .and_then(|value| value.to_str().ok())
+        .and_then(parse_content_range_start)
+}
+
+// Parse a header value like `bytes 100-999/1000` into `100`
+fn parse_content_range_start(value: &str) -> Option<u64> {
+    let range = value.trim().strip_prefix("bytes ")?;
+    let (start, _) = range.split_once('-')?;
+    start.trim().parse().ok()
+}
+
 // Percent-decoding function (URL decode)
 fn percent_decode(input: &str) -> String {
     let mut bytes = Vec::new();
@@ -214,4 +229,12 @@ mod test {
             println!("Can't find osu.exe.");
         }
     }
+
+    #[test]
+    pub fn test_parse_content_range_start() {
+        assert_eq!(parse_content_range_start("bytes 100-999/1000"), Some(100));
+        assert_eq!(parse_content_range_start("bytes 0-0/*"), Some(0));
+        assert_eq!(parse_content_range_start("bytes */1000"), None);
+        assert_eq!(parse_content_range_start("items 1-2/3"), None);
+    }
 }
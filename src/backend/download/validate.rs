@@ -0,0 +1,111 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+};
+
+use zip::ZipArchive;
+
+use crate::backend::error::{Error, Result};
+
+/// Check that a downloaded file is a usable beatmapset archive
+///
+/// The file must open as a zip and contain at least one `.osu` file. With `expected_set_id`,
+/// the `BeatmapSetID` of the first difficulty must match it, unless the difficulty predates that field.
+pub fn validate_archive(path: &Path, expected_set_id: Option<u32>) -> Result<()> {
+    let file = File::open(path)?;
+    let mut archive = ZipArchive::new(file).map_err(|e| invalid(format!("Not a valid zip archive: {}", e)))?;
+
+    let osu_file = archive
+        .file_names()
+        .find(|name| name.to_lowercase().ends_with(".osu"))
+        .map(str::to_owned)
+        .ok_or_else(|| invalid("Archive contains no .osu file".to_string()))?;
+
+    if let Some(expected) = expected_set_id {
+        let entry = archive
+            .by_name(&osu_file)
+            .map_err(|e| invalid(format!("Can't read {}: {}", osu_file, e)))?;
+        let set_id = read_set_id(BufReader::new(entry))
+            .map_err(|e| invalid(format!("Can't read {}: {}", osu_file, e)))?;
+        if let Some(found) = set_id {
+            if found != expected {
+                return Err(invalid(format!(
+                    "Archive belongs to beatmapset {} instead of {}",
+                    found, expected
+                )));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Read `BeatmapSetID` from the `[Metadata]` section of a .osu file
+///
+/// Returns `None` for old files without the field or with a placeholder ID of -1.
+fn read_set_id(reader: impl BufRead) -> io::Result<Option<u32>> {
+    for line in reader.lines() {
+        let line = line?;
+        let line = line.trim();
+        if let Some(value) = line.strip_prefix("BeatmapSetID:") {
+            return Ok(value.trim().parse().ok());
+        }
+        // Sections after [Metadata] never contain the ID
+        if line == "[Difficulty]" || line == "[HitObjects]" {
+            break;
+        }
+    }
+    Ok(None)
+}
+
+fn invalid(msg: String) -> Error {
+    Error::InvalidArchive(msg)
+}
+
+#[cfg(test)]
+mod test {
+    use std::{fs, io::Write, path::PathBuf};
+
+    use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+    use crate::backend::download::validate::*;
+    use crate::backend::test_util::temp_path;
+
+    fn write_archive(name: &str, entries: &[(&str, &str)]) -> PathBuf {
+        let path = temp_path(&format!("{}.osz", name));
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+        for (entry, content) in entries {
+            writer.start_file(*entry, options).unwrap();
+            writer.write_all(content.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+        path
+    }
+
+    #[test]
+    pub fn test_validate_archive_checks_set_id() {
+        let osu = "osu file format v14\n\n[Metadata]\nTitle:Test\nBeatmapSetID:42\n\n[Difficulty]\nHPDrainRate:5\n";
+        let path = write_archive("set-id", &[("audio.mp3", ""), ("Test [Hard].osu", osu)]);
+        assert!(validate_archive(&path, None).is_ok());
+        assert!(validate_archive(&path, Some(42)).is_ok());
+        assert!(matches!(validate_archive(&path, Some(43)), Err(Error::InvalidArchive(_))));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_validate_archive_rejects_missing_osu_file() {
+        let path = write_archive("no-osu", &[("audio.mp3", "")]);
+        assert!(validate_archive(&path, None).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    pub fn test_validate_archive_rejects_html_page() {
+        let path = temp_path("html.osz");
+        fs::write(&path, "<html><body>Beatmap not found</body></html>").unwrap();
+        assert!(validate_archive(&path, None).is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+use std::{fmt, io, path::PathBuf, time::Duration};
+
+use reqwest::StatusCode;
+use rosu_v2::error::OsuError;
+
+use crate::backend::download::PackageVariant;
+
+/// Everything that can go wrong while searching and downloading beatmaps
+#[derive(Debug)]
+pub enum Error {
+    /// No usable answer: connection failure, timeout or a broken stream
+    Network(reqwest::Error),
+    /// The server answered with an error status
+    Http { status: StatusCode, url: String },
+    Io(io::Error),
+    /// The mirror answered with something that isn't a download
+    InvalidResponse(String),
+    /// The downloaded file isn't a usable beatmapset archive
+    InvalidArchive(String),
+    /// Signing in to the osu! API failed
+    Auth(OsuError),
+    /// The osu! API failed a search
+    Search(OsuError),
+    /// The osu! API knows no user by this name
+    UnknownUser(String),
+    /// The search went through but matched nothing
+    NoResults,
+    /// The osu! folder was found, but its songs directory can't be read
+    GameDir { path: PathBuf, source: io::Error },
+    /// A list file couldn't be written or read back
+    InvalidList(String),
+    /// None of the selected mirrors serves the requested package
+    NoMirror(PackageVariant),
+    /// The mirror asked us to back off for longer than is worth waiting
+    MirrorPaused { mirror: &'static str, pause: Duration },
+    Cancelled,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Network(e) => write!(f, "Network error: {}", e),
+            Error::Http { status, url } => write!(f, "{} answered {}", url, status),
+            Error::Io(e) => write!(f, "IO error: {}", e),
+            Error::InvalidResponse(msg) => write!(f, "Invalid response: {}", msg),
+            Error::InvalidArchive(msg) => write!(f, "Invalid archive: {}", msg),
+            Error::Auth(e) => write!(f, "Sign in failed: {}", e),
+            Error::Search(e) => write!(f, "Search failed: {}", e),
+            Error::UnknownUser(name) => write!(f, "No osu! user named {}", name),
+            Error::NoResults => write!(f, "No beatmaps found"),
+            Error::GameDir { path, source } => {
+                write!(f, "Can't read the songs directory {}: {}", path.display(), source)
+            }
+            Error::InvalidList(msg) => write!(f, "Invalid list file: {}", msg),
+            Error::NoMirror(variant) => {
+                write!(f, "None of the selected mirrors serves {} packages", variant)
+            }
+            Error::MirrorPaused { mirror, pause } => {
+                write!(f, "{} asked to back off for {}s", mirror, pause.as_secs())
+            }
+            Error::Cancelled => write!(f, "Cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Network(e) => Some(e),
+            Error::Io(e) | Error::GameDir { source: e, .. } => Some(e),
+            Error::Auth(e) | Error::Search(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        match e.status() {
+            Some(status) => Error::Http {
+                status,
+                url: e.url().map(|url| url.to_string()).unwrap_or_default(),
+            },
+            None => Error::Network(e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
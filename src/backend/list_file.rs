@@ -0,0 +1,190 @@
+use std::{fs::File, path::Path};
+
+use rosu_v2::prelude::{GameMode, RankStatus};
+use serde::{Deserialize, Serialize};
+
+use crate::backend::error::{Error, Result};
+use crate::backend::osu::{owned_or_empty, MapsetInfo};
+
+/// File format of a shared list of mapsets, picked by the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListFormat {
+    Csv,
+    Json,
+}
+
+impl ListFormat {
+    fn from_path(path: &Path) -> Result<Self> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("csv") => Ok(ListFormat::Csv),
+            Some("json") => Ok(ListFormat::Json),
+            _ => Err(Error::InvalidList(String::from("the file name must end in .csv or .json"))),
+        }
+    }
+}
+
+/// One row of a list file
+#[derive(Debug, Serialize, Deserialize)]
+struct ListEntry {
+    mapset_id: u32,
+    artist: String,
+    title: String,
+    creator: String,
+    status: String,
+    /// Game modes separated by spaces, may be empty
+    #[serde(default)]
+    mode: String,
+}
+
+const fn status_name(status: RankStatus) -> &'static str {
+    match status {
+        RankStatus::Graveyard => "graveyard",
+        RankStatus::WIP => "wip",
+        RankStatus::Pending => "pending",
+        RankStatus::Ranked => "ranked",
+        RankStatus::Approved => "approved",
+        RankStatus::Qualified => "qualified",
+        RankStatus::Loved => "loved",
+    }
+}
+
+const fn mode_name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::Osu => "osu",
+        GameMode::Taiko => "taiko",
+        GameMode::Catch => "fruits",
+        GameMode::Mania => "mania",
+    }
+}
+
+impl ListEntry {
+    fn new(mapset: &MapsetInfo) -> Self {
+        let modes: Vec<&str> = mapset.modes.iter().map(|&mode| mode_name(mode)).collect();
+        Self {
+            mapset_id: mapset.mapset_id,
+            artist: mapset.artist.clone(),
+            title: mapset.title.clone(),
+            creator: mapset.creator.clone(),
+            status: status_name(mapset.status).to_string(),
+            mode: modes.join(" "),
+        }
+    }
+
+    /// Ownership isn't part of the file, it depends on the machine the list is imported on
+    fn into_mapset(self, owned: bool) -> Result<MapsetInfo> {
+        let invalid = |what: &str, value: &str| {
+            Error::InvalidList(format!("set {} has an unknown {} {}", self.mapset_id, what, value))
+        };
+        let status = [
+            RankStatus::Graveyard,
+            RankStatus::WIP,
+            RankStatus::Pending,
+            RankStatus::Ranked,
+            RankStatus::Approved,
+            RankStatus::Qualified,
+            RankStatus::Loved,
+        ]
+        .into_iter()
+        .find(|&status| status_name(status).eq_ignore_ascii_case(self.status.trim()))
+        .ok_or_else(|| invalid("status", &self.status))?;
+        let modes = self
+            .mode
+            .split_whitespace()
+            .map(|name| {
+                [GameMode::Osu, GameMode::Taiko, GameMode::Catch, GameMode::Mania]
+                    .into_iter()
+                    .find(|&mode| mode_name(mode).eq_ignore_ascii_case(name))
+                    .ok_or_else(|| invalid("mode", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(MapsetInfo {
+            mapset_id: self.mapset_id,
+            artist: self.artist,
+            title: self.title,
+            creator: self.creator,
+            status,
+            modes,
+            stars: None,
+            owned,
+        })
+    }
+}
+
+/// Write mapsets to a CSV or JSON file, the extension of `path` decides which
+pub fn export_list(path: &Path, mapsets: &[MapsetInfo]) -> Result<()> {
+    let format = ListFormat::from_path(path)?;
+    let entries: Vec<ListEntry> = mapsets.iter().map(ListEntry::new).collect();
+    let file = File::create(path)?;
+    match format {
+        ListFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(file);
+            for entry in &entries {
+                writer.serialize(entry).map_err(|e| Error::InvalidList(e.to_string()))?;
+            }
+            writer.flush()?;
+        }
+        ListFormat::Json => {
+            serde_json::to_writer_pretty(file, &entries).map_err(|e| Error::InvalidList(e.to_string()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Read mapsets from a CSV or JSON file written by `export_list`
+pub fn import_list(path: &Path) -> Result<Vec<MapsetInfo>> {
+    let format = ListFormat::from_path(path)?;
+    let file = File::open(path)?;
+    let entries: Vec<ListEntry> = match format {
+        ListFormat::Csv => csv::Reader::from_reader(file)
+            .deserialize()
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|e| Error::InvalidList(e.to_string()))?,
+        ListFormat::Json => serde_json::from_reader(file).map_err(|e| Error::InvalidList(e.to_string()))?,
+    };
+    let owned = owned_or_empty();
+    entries
+        .into_iter()
+        .map(|entry| {
+            let is_owned = owned.contains(&entry.mapset_id);
+            entry.into_mapset(is_owned)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::list_file::*;
+    use crate::backend::test_util::{self, temp_path};
+
+    fn mapset(mapset_id: u32, modes: Vec<GameMode>) -> MapsetInfo {
+        MapsetInfo {
+            artist: "Artist, with comma".to_string(),
+            title: "Title \"quoted\"".to_string(),
+            status: RankStatus::Loved,
+            modes,
+            owned: true,
+            ..test_util::mapset(mapset_id)
+        }
+    }
+
+    #[test]
+    pub fn test_round_trip() {
+        let mapsets = vec![mapset(1, vec![GameMode::Osu, GameMode::Mania]), mapset(2, Vec::new())];
+        for name in ["list.csv", "list.JSON"] {
+            let path = temp_path(name);
+            export_list(&path, &mapsets).unwrap();
+            let imported = import_list(&path).unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            assert_eq!(imported.len(), 2);
+            assert_eq!(imported[0].mapset_id, 1);
+            assert_eq!(imported[0].artist, "Artist, with comma");
+            assert_eq!(imported[0].title, "Title \"quoted\"");
+            assert_eq!(imported[0].status, RankStatus::Loved);
+            assert_eq!(imported[0].modes, vec![GameMode::Osu, GameMode::Mania]);
+            assert!(imported[1].modes.is_empty());
+        }
+        assert!(matches!(export_list(&temp_path("list.txt"), &mapsets), Err(Error::InvalidList(_))));
+    }
+}
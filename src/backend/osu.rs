@@ -1,9 +1,38 @@
-use rosu_v2::{error::OsuError, prelude::GameMode, Osu};
+use std::{
+    collections::HashSet,
+    future::IntoFuture,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use futures_util::TryFutureExt;
+use rosu_v2::{
+    error::OsuError,
+    prelude::{Beatmapset, BeatmapsetExtended, BeatmapsetSearchResult, GameMode, RankStatus, UserExtended},
+    Osu,
+};
+
+use crate::backend::download::{owned_mapset_ids, retry, RetryPolicy};
+use crate::backend::error::{Error, Result};
+pub use filter::{Bounds, SearchFilters};
+pub use links::{parse_map_refs, resolve_map_refs, ResolvedLinks};
+pub use lists::{user_maps, UserList, UserMapsConfig};
+pub use packs::{list_packs, pack_maps, parse_pack_tags, PackInfo, PackPage, PackType, PacksConfig};
+
+mod filter;
+mod links;
+mod lists;
+mod packs;
 
 pub struct SearchConfig {
     game_mode: GameMode,
     mapper: Option<String>,
+    guest_difficulties: bool,
     custom_query: Option<String>,
+    filters: SearchFilters,
+    limits: ListingLimits,
 }
 
 impl SearchConfig {
@@ -11,10 +40,26 @@ impl SearchConfig {
         Self {
             game_mode: GameMode::Mania,
             mapper: None,
+            guest_difficulties: false,
             custom_query: None,
+            filters: SearchFilters::default(),
+            limits: ListingLimits::new(),
         }
     }
 
+    #[inline]
+    pub fn filters(mut self, filters: SearchFilters) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// When to stop paging, a page being one page of search results
+    #[inline]
+    pub fn limits(mut self, limits: ListingLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
     #[inline]
     pub const fn game_mode(mut self, game_mode: GameMode) -> Self {
         self.game_mode = game_mode;
@@ -27,6 +72,13 @@ impl SearchConfig {
         self
     }
 
+    /// Also keep other mappers' sets the mapper made a guest difficulty for
+    #[inline]
+    pub const fn guest_difficulties(mut self, guest_difficulties: bool) -> Self {
+        self.guest_difficulties = guest_difficulties;
+        self
+    }
+
     #[inline]
     pub fn custom_query(mut self, custom_query: String) -> Self {
         self.custom_query = Some(custom_query);
@@ -34,69 +86,357 @@ impl SearchConfig {
     }
 }
 
-pub async fn login() -> Result<Osu, OsuError> {
+pub async fn login() -> Result<Osu> {
     // Login with secret id and password
     // Using token of my own account for easy config, make good use of it please !
     println!("Signing in...");
     let client_id: u64 = 47208;
     let client_secret = String::from("D400j2fmT5xWN55uuj51r4EGgTnweSZLItPJhvgu");
 
-    Osu::new(client_id, client_secret).await
+    Osu::new(client_id, client_secret).await.map_err(Error::Auth)
+}
+
+/// When a search or listing stops asking for more, and the handle it reports to
+pub struct ListingLimits {
+    max_results: Option<usize>,
+    max_pages: Option<usize>,
+    handle: Arc<SearchHandle>,
+}
+
+impl ListingLimits {
+    pub fn new() -> Self {
+        Self {
+            max_results: None,
+            max_pages: None,
+            handle: Arc::new(SearchHandle::new()),
+        }
+    }
+
+    /// Stop once this many mapsets are collected
+    #[inline]
+    pub const fn max_results(mut self, max_results: usize) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// Stop after this many requests
+    #[inline]
+    pub const fn max_pages(mut self, max_pages: usize) -> Self {
+        self.max_pages = Some(max_pages);
+        self
+    }
+
+    /// Live count and stop switch, keep a clone around to watch the search
+    #[inline]
+    pub fn handle(mut self, handle: Arc<SearchHandle>) -> Self {
+        self.handle = handle;
+        self
+    }
+
+    /// Whether paging should end with `found` mapsets from `pages` requests
+    fn reached_limit(&self, found: usize, pages: usize) -> bool {
+        self.max_results.is_some_and(|max| found >= max)
+            || self.max_pages.is_some_and(|max| pages >= max)
+            || self.handle.is_stopped()
+    }
+
+    /// Cut `mapsets` down to the result limit and report the counts to the handle
+    fn record(&self, mapsets: &mut Vec<MapsetInfo>, pages: usize) {
+        if let Some(max) = self.max_results {
+            mapsets.truncate(max);
+        }
+        self.handle.found.store(mapsets.len(), Ordering::Relaxed);
+        self.handle.pages.store(pages, Ordering::Relaxed);
+    }
+}
+
+/// Live state of a running search, shared with whoever started it
+pub struct SearchHandle {
+    found: AtomicUsize,
+    pages: AtomicUsize,
+    stopped: AtomicBool,
+}
+
+impl SearchHandle {
+    pub fn new() -> Self {
+        Self {
+            found: AtomicUsize::new(0),
+            pages: AtomicUsize::new(0),
+            stopped: AtomicBool::new(false),
+        }
+    }
+
+    /// Mapsets collected and pages looked up so far
+    pub fn counts(&self) -> (usize, usize) {
+        (self.found.load(Ordering::Relaxed), self.pages.load(Ordering::Relaxed))
+    }
+
+    /// End paging after the current page and go on with what was found
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::Relaxed)
+    }
+}
+
+/// What the GUI shows about a found mapset
+#[derive(Debug, Clone)]
+pub struct MapsetInfo {
+    pub mapset_id: u32,
+    pub artist: String,
+    pub title: String,
+    pub creator: String,
+    pub status: RankStatus,
+    /// Game modes of its difficulties, empty if they weren't included
+    pub modes: Vec<GameMode>,
+    /// Lowest and highest star rating of its difficulties, if they were included
+    pub stars: Option<(f32, f32)>,
+    /// The set is already in the osu! songs directory
+    pub owned: bool,
+}
+
+impl MapsetInfo {
+    pub fn new(mapset: &BeatmapsetExtended, owned: bool) -> Self {
+        let stars = mapset.maps.as_deref().and_then(|maps| {
+            let stars = maps.iter().map(|map| map.stars);
+            let min = stars.clone().reduce(f32::min)?;
+            let max = stars.reduce(f32::max)?;
+            Some((min, max))
+        });
+        let mut modes = Vec::new();
+        for map in mapset.maps.iter().flatten() {
+            if !modes.contains(&map.mode) {
+                modes.push(map.mode);
+            }
+        }
+        Self {
+            mapset_id: mapset.mapset_id,
+            artist: mapset.artist.clone(),
+            title: mapset.title.clone(),
+            creator: mapset.creator_name.to_string(),
+            status: mapset.status,
+            modes,
+            stars,
+            owned,
+        }
+    }
+
+    /// From a set without its difficulties, as the score and most played endpoints return them
+    pub fn from_mapset(mapset: &Beatmapset, owned: bool) -> Self {
+        Self {
+            mapset_id: mapset.mapset_id,
+            artist: mapset.artist.clone(),
+            title: mapset.title.clone(),
+            creator: mapset.creator_name.to_string(),
+            status: mapset.status,
+            modes: Vec::new(),
+            stars: None,
+            owned,
+        }
+    }
+}
+
+/// Look up a user by name, so renames and similar names don't matter
+async fn resolve_user(osu: &Osu, name: &str, retry_policy: &RetryPolicy) -> Result<UserExtended> {
+    let (user, _) = retry(retry_policy, |_| {
+        osu.user(name).into_future().map_err(|e| match e {
+            OsuError::NotFound => Error::UnknownUser(name.to_string()),
+            e => Error::Search(e),
+        })
+    })
+    .await;
+    user
 }
 
-pub async fn search_maps(osu: &Osu, config: SearchConfig) -> Vec<u32> {
-    let game_mode = config.game_mode;
+/// Sets in the songs directory
+///
+/// Knowing what is owned is a convenience, a search doesn't fail over it.
+pub(crate) fn owned_or_empty() -> HashSet<u32> {
+    owned_mapset_ids()
+        .unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            None
+        })
+        .unwrap_or_default()
+}
+
+/// Whether `user_id` mapped the set, or with `guest` one of `diff_creators`' difficulties
+fn made_by(
+    creator_id: u32,
+    mut diff_creators: impl Iterator<Item = u32>,
+    user_id: u32,
+    guest: bool,
+) -> bool {
+    creator_id == user_id || (guest && diff_creators.any(|id| id == user_id))
+}
+
+/// Mapsets found by a search
+pub struct SearchResult {
+    pub mapsets: Vec<MapsetInfo>,
+    /// More pages were left when a limit or a stop request ended the search
+    pub limited: bool,
+    /// Page the search stopped at and why, the pages before it are in `mapsets`
+    pub failed_page: Option<(usize, Error)>,
+}
+
+impl SearchResult {
+    /// What was collected before request `page` failed
+    fn partial(mapsets: Vec<MapsetInfo>, page: usize, e: Error) -> Self {
+        eprintln!("Collected {} beatmaps before request {} failed: {}", mapsets.len(), page, e);
+        Self {
+            mapsets,
+            limited: false,
+            failed_page: Some((page, e)),
+        }
+    }
+}
+
+/// Search for mapsets, transient API errors are retried
+///
+/// Sets keep the creator name they were uploaded under, so a mapper search runs once for each name
+/// the mapper went by. Fails if not even the first page could be fetched. A later page failing ends
+/// the search with what was collected so far.
+pub async fn search_maps(osu: &Osu, config: SearchConfig) -> Result<SearchResult> {
+    let retry_policy = RetryPolicy::new();
+    let mapper = match &config.mapper {
+        Some(mapper) => Some(resolve_user(osu, mapper, &retry_policy).await?),
+        None => None,
+    };
+    let mapper_id = mapper.as_ref().map(|user| user.user_id);
+    let mapper_names: Vec<Option<String>> = match &mapper {
+        Some(user) => std::iter::once(&user.username)
+            .chain(user.previous_usernames.iter().flatten())
+            .map(|name| Some(name.to_string()))
+            .collect(),
+        None => vec![None],
+    };
+
+    let owned = owned_or_empty();
+    let mut seen = HashSet::new();
+    let mut all_mapsets: Vec<MapsetInfo> = Vec::new();
+    let mut page = 0;
+    let mut limited = false;
+    'names: for (i, mapper_name) in mapper_names.iter().enumerate() {
+        if i > 0 && config.limits.reached_limit(all_mapsets.len(), page) {
+            limited = true;
+            break;
+        }
+        let query = search_query(mapper_name.as_deref(), &config);
+        println!("Searching beatmaps use query: {}", &query);
+        page += 1;
+        //Search Maps
+        let (first_page, _) = retry(&retry_policy, |_| first_search_page(osu, &query, &config)).await;
+        let mut found_maps = match first_page {
+            Ok(found_maps) => found_maps,
+            Err(e) if i == 0 => return Err(e),
+            Err(e) => return Ok(SearchResult::partial(all_mapsets, page, e)),
+        };
+        loop {
+            let mut mapset_lst: Vec<MapsetInfo> = found_maps
+                .mapsets
+                .iter()
+                .filter(|map| {
+                    // The text search also matches tags and titles, keep only what the mapper made
+                    mapper_id.is_none_or(|user_id| {
+                        let diff_creators = map.maps.iter().flatten().map(|diff| diff.creator_id);
+                        made_by(map.creator_id, diff_creators, user_id, config.guest_difficulties)
+                    })
+                })
+                .filter(|map| seen.insert(map.mapset_id))
+                .map(|map| MapsetInfo::new(map, owned.contains(&map.mapset_id)))
+                .collect();
+            all_mapsets.append(&mut mapset_lst);
+            config.limits.record(&mut all_mapsets, page);
+            if !found_maps.has_more() {
+                break;
+            }
+            if config.limits.reached_limit(all_mapsets.len(), page) {
+                println!("Stopping the search after page {}", page);
+                limited = true;
+                break 'names;
+            }
+            page += 1;
+            println!("Looking up page {}", page);
+            let (next_page, _) = retry(&retry_policy, |_| async {
+                found_maps.get_next(osu).await.transpose().map_err(Error::Search)
+            })
+            .await;
+            match next_page {
+                Ok(Some(next)) => found_maps = next,
+                Ok(None) => break,
+                Err(e) => return Ok(SearchResult::partial(all_mapsets, page, e)),
+            }
+        }
+    }
+    println!("Collected {} beatmaps.", all_mapsets.len());
+
+    Ok(SearchResult {
+        mapsets: all_mapsets,
+        limited,
+        failed_page: None,
+    })
+}
+
+/// Query text for a search by `mapper_name`, followed by the filters and the custom query
+fn search_query(mapper_name: Option<&str>, config: &SearchConfig) -> String {
     let mut query = String::new();
-    if let Some(mapper) = &config.mapper {
+    if let Some(mapper) = mapper_name {
         query.push_str(format!("{} ", mapper).as_str());
     }
-    if let Some(custom_query) = config.custom_query {
+    let filter_query = config.filters.query();
+    if !filter_query.is_empty() {
+        query.push_str(format!("{} ", filter_query).as_str());
+    }
+    // Free text goes last, so power users can still add any term the filters don't cover
+    if let Some(custom_query) = &config.custom_query {
         query.push_str(custom_query.as_str());
     }
+    query
+}
 
-    println!("Searching beatmaps use query: {}", &query);
-    //Search Maps
-    let mut found_maps = osu
+async fn first_search_page(osu: &Osu, query: &str, config: &SearchConfig) -> Result<BeatmapsetSearchResult> {
+    let filters = &config.filters;
+    let mut search = osu
         .beatmapset_search()
         .nsfw(false)
-        .status(None)
-        .mode(game_mode)
-        .query(query)
-        .await
-        .unwrap();
-    let mut all_mapset_ids: Vec<u32> = Vec::new();
-    let mut page = 1;
-    loop {
-        let mut mapset_lst: Vec<u32> = found_maps
-            .mapsets
-            .iter()
-            .filter(|map| {
-                if let Some(mapper) = &config.mapper {
-                    let found = map.creator_name.to_lowercase();
-                    let query = mapper.to_lowercase();
-                    found.contains(query.as_str())
-                } else {
-                    true
-                }
-            }) // make sure creator_name is equal to mapper
-            .map(|map| {
-                // println!("Debug: Find map name {}", map.title);
-                map.mapset_id
-            })
-            .collect();
-        all_mapset_ids.append(&mut mapset_lst);
-        if found_maps.has_more() {
-            println!("Looking up page {}", page.to_string());
-            found_maps = found_maps.get_next(&osu).await.unwrap().unwrap();
-            page += 1;
-        } else {
-            println!(
-                "Collected {} beatmaps by this mapper.",
-                all_mapset_ids.len()
-            );
-            break;
-        }
+        .status(filters.status)
+        .mode(config.game_mode)
+        .video(filters.video)
+        .storyboard(filters.storyboard)
+        .query(query);
+    if let Some(genre) = filters.genre {
+        search = search.genre(genre);
+    }
+    if let Some(language) = filters.language {
+        search = search.language(language);
     }
+    search.await.map_err(Error::Search)
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::osu::*;
+
+    #[test]
+    pub fn test_reached_limit() {
+        let limits = ListingLimits::new().max_results(100).max_pages(3);
+        assert!(!limits.reached_limit(99, 2));
+        assert!(limits.reached_limit(100, 2));
+        assert!(limits.reached_limit(10, 3));
 
-    all_mapset_ids
+        let limits = ListingLimits::new();
+        assert!(!limits.reached_limit(10_000, 200));
+        limits.handle.stop();
+        assert!(limits.reached_limit(0, 1));
+    }
+
+    #[test]
+    pub fn test_made_by() {
+        assert!(made_by(1, [2, 3].into_iter(), 1, false));
+        assert!(!made_by(2, [2, 1].into_iter(), 1, false));
+        assert!(made_by(2, [2, 1].into_iter(), 1, true));
+        assert!(!made_by(2, [2, 3].into_iter(), 1, true));
+    }
 }
@@ -0,0 +1,98 @@
+use std::fmt::Display;
+
+use rosu_v2::prelude::{Genre, Language, RankStatus};
+
+/// Lower and upper bound of a filter, either may be left open
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: Display> Bounds<T> {
+    fn compile(&self, key: &str, terms: &mut Vec<String>) {
+        if let Some(min) = &self.min {
+            terms.push(format!("{}>={}", key, min));
+        }
+        if let Some(max) = &self.max {
+            terms.push(format!("{}<={}", key, max));
+        }
+    }
+}
+
+/// Typed search filters
+///
+/// Numeric and date filters become osu! search query terms, the others are search parameters.
+#[derive(Debug, Clone, Default)]
+pub struct SearchFilters {
+    /// `None` matches every status
+    pub status: Option<RankStatus>,
+    pub stars: Bounds<f32>,
+    pub ar: Bounds<f32>,
+    pub od: Bounds<f32>,
+    pub cs: Bounds<f32>,
+    pub hp: Bounds<f32>,
+    pub bpm: Bounds<f32>,
+    /// Length in seconds
+    pub length: Bounds<u32>,
+    /// Key count, only meaningful for mania
+    pub keys: Option<u32>,
+    pub genre: Option<Genre>,
+    pub language: Option<Language>,
+    /// Only sets with a video
+    pub video: bool,
+    /// Only sets with a storyboard
+    pub storyboard: bool,
+    /// Ranked date as `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+    pub ranked: Bounds<String>,
+}
+
+impl SearchFilters {
+    /// The query terms of the filters, e.g. `stars>=4 ar<=9 keys=7`
+    pub fn query(&self) -> String {
+        let mut terms = Vec::new();
+        self.stars.compile("stars", &mut terms);
+        self.ar.compile("ar", &mut terms);
+        self.od.compile("od", &mut terms);
+        self.cs.compile("cs", &mut terms);
+        self.hp.compile("hp", &mut terms);
+        self.bpm.compile("bpm", &mut terms);
+        self.length.compile("length", &mut terms);
+        if let Some(keys) = self.keys {
+            terms.push(format!("keys={}", keys));
+        }
+        self.ranked.compile("ranked", &mut terms);
+        terms.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::osu::filter::*;
+
+    #[test]
+    pub fn test_query() {
+        assert_eq!(SearchFilters::default().query(), "");
+
+        let filters = SearchFilters {
+            stars: Bounds {
+                min: Some(4.5),
+                max: Some(6.0),
+            },
+            length: Bounds {
+                min: None,
+                max: Some(180),
+            },
+            keys: Some(7),
+            ranked: Bounds {
+                min: Some("2020-01-01".to_string()),
+                max: None,
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            filters.query(),
+            "stars>=4.5 stars<=6 length<=180 keys=7 ranked>=2020-01-01"
+        );
+    }
+}
@@ -0,0 +1,150 @@
+use std::{collections::HashSet, future::IntoFuture};
+
+use futures_util::TryFutureExt;
+use reqwest::Url;
+use rosu_v2::Osu;
+
+use crate::backend::download::{retry, RetryPolicy};
+use crate::backend::error::{Error, Result};
+
+/// The beatmaps endpoint takes at most this many IDs per request
+const BEATMAPS_PER_REQUEST: usize = 50;
+
+/// A map named by a link or ID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MapRef {
+    Mapset(u32),
+    /// A single difficulty, downloaded as its whole set
+    Beatmap(u32),
+}
+
+/// Leading digits of `s`, old links append parameters like `456&m=0`
+fn leading_id(s: &str) -> Option<u32> {
+    let end = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    s[..end].parse().ok()
+}
+
+/// Parse a bare mapset ID or an osu! link, old site links included
+fn parse_map_ref(token: &str) -> Option<MapRef> {
+    if let Ok(id) = token.parse() {
+        return Some(MapRef::Mapset(id));
+    }
+    let url = Url::parse(token)
+        .or_else(|_| Url::parse(&format!("https://{}", token)))
+        .ok()?;
+    let host = url.host_str()?;
+    if host != "ppy.sh" && !host.ends_with(".ppy.sh") {
+        return None;
+    }
+    let segments: Vec<&str> = url.path_segments()?.filter(|segment| !segment.is_empty()).collect();
+    match segments.as_slice() {
+        // The difficulty in `#mania/456` doesn't matter, the whole set is downloaded
+        ["beatmapsets", id, ..] | ["s", id, ..] => leading_id(id).map(MapRef::Mapset),
+        ["beatmaps", id, ..] | ["b", id, ..] => leading_id(id).map(MapRef::Beatmap),
+        // Old site, e.g. `/p/beatmap?b=456&m=0`
+        _ => url.query_pairs().find_map(|(key, value)| match key.as_ref() {
+            "b" => leading_id(&value).map(MapRef::Beatmap),
+            "s" => leading_id(&value).map(MapRef::Mapset),
+            _ => None,
+        }),
+    }
+}
+
+/// Parse a list of links and IDs separated by whitespace or commas
+///
+/// Bare numbers are taken as mapset IDs. Returns what was understood and what wasn't.
+pub fn parse_map_refs(input: &str) -> (Vec<MapRef>, Vec<String>) {
+    let mut refs = Vec::new();
+    let mut unknown = Vec::new();
+    for token in input.split(|c: char| c == ',' || c.is_whitespace()).filter(|token| !token.is_empty()) {
+        match parse_map_ref(token) {
+            Some(map_ref) => refs.push(map_ref),
+            None => unknown.push(token.to_string()),
+        }
+    }
+    (refs, unknown)
+}
+
+/// Mapsets of a list of links
+#[derive(Debug)]
+pub struct ResolvedLinks {
+    /// In the order of the list, without repeats
+    pub mapset_ids: Vec<u32>,
+    /// Beatmaps the API doesn't know, deleted or mistyped
+    pub unknown_maps: Vec<u32>,
+}
+
+/// Look up the sets of the linked difficulties, linked sets are taken as they are
+pub async fn resolve_map_refs(osu: &Osu, refs: &[MapRef]) -> Result<ResolvedLinks> {
+    let retry_policy = RetryPolicy::new();
+    let map_ids: Vec<u32> = refs
+        .iter()
+        .filter_map(|map_ref| match map_ref {
+            MapRef::Beatmap(map_id) => Some(*map_id),
+            MapRef::Mapset(_) => None,
+        })
+        .collect();
+    let mut map_sets = Vec::new();
+    for chunk in map_ids.chunks(BEATMAPS_PER_REQUEST) {
+        println!("Looking up the sets of {} beatmaps", chunk.len());
+        let (maps, _) = retry(&retry_policy, |_| {
+            osu.beatmaps(chunk.iter().copied()).into_future().map_err(Error::Search)
+        })
+        .await;
+        map_sets.extend(maps?.into_iter().map(|map| (map.map_id, map.mapset_id)));
+    }
+
+    let mut seen = HashSet::new();
+    let mut mapset_ids = Vec::new();
+    let mut unknown_maps = Vec::new();
+    for map_ref in refs {
+        let mapset_id = match *map_ref {
+            MapRef::Mapset(mapset_id) => mapset_id,
+            MapRef::Beatmap(map_id) => match map_sets.iter().find(|(id, _)| *id == map_id) {
+                Some(&(_, mapset_id)) => mapset_id,
+                None => {
+                    unknown_maps.push(map_id);
+                    continue;
+                }
+            },
+        };
+        if seen.insert(mapset_id) {
+            mapset_ids.push(mapset_id);
+        }
+    }
+    Ok(ResolvedLinks {
+        mapset_ids,
+        unknown_maps,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::osu::links::*;
+
+    #[test]
+    pub fn test_parse_map_refs() {
+        let input = "https://osu.ppy.sh/beatmapsets/123#mania/456, https://osu.ppy.sh/b/456\n\
+            osu.ppy.sh/s/789 https://osu.ppy.sh/beatmaps/42?mode=osu\n\
+            https://old.ppy.sh/p/beatmap?b=77&m=3 https://osu.ppy.sh/b/88&m=0 1001 \
+            https://example.com/s/5 not-a-link https://evilppy.sh/s/6 notppy.sh/b/7 https://ppy.sh/s/8";
+        let (refs, unknown) = parse_map_refs(input);
+        assert_eq!(
+            refs,
+            vec![
+                MapRef::Mapset(123),
+                MapRef::Beatmap(456),
+                MapRef::Mapset(789),
+                MapRef::Beatmap(42),
+                MapRef::Beatmap(77),
+                MapRef::Beatmap(88),
+                MapRef::Mapset(1001),
+                MapRef::Mapset(8),
+            ]
+        );
+        assert_eq!(
+            unknown,
+            vec!["https://example.com/s/5", "not-a-link", "https://evilppy.sh/s/6", "notppy.sh/b/7"]
+        );
+    }
+}
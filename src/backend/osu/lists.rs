@@ -0,0 +1,229 @@
+use std::{collections::HashSet, fmt, future::IntoFuture};
+
+use futures_util::TryFutureExt;
+use rosu_v2::{
+    prelude::{GameMode, UserBeatmapsetsKind},
+    Osu,
+};
+
+use crate::backend::download::{retry, RetryPolicy};
+use crate::backend::error::{Error, Result};
+use crate::backend::osu::{owned_or_empty, resolve_user, ListingLimits, MapsetInfo, SearchResult};
+
+/// How many entries the user endpoints return per request
+const PAGE_SIZE: usize = 100;
+
+/// A list of maps tied to a user
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserList {
+    /// Both ranked and approved uploads
+    Ranked,
+    Loved,
+    Pending,
+    Graveyard,
+    /// Other mappers' sets the user made a guest difficulty for
+    Guest,
+    Favourites,
+    MostPlayed,
+    /// The 100 best scores in the selected game mode
+    TopPlays,
+}
+
+impl UserList {
+    /// The category of the user beatmapsets endpoint, if the list comes from there
+    const fn beatmapsets_kind(self) -> Option<UserBeatmapsetsKind> {
+        match self {
+            UserList::Ranked => Some(UserBeatmapsetsKind::Ranked),
+            UserList::Loved => Some(UserBeatmapsetsKind::Loved),
+            UserList::Pending => Some(UserBeatmapsetsKind::Pending),
+            UserList::Graveyard => Some(UserBeatmapsetsKind::Graveyard),
+            UserList::Guest => Some(UserBeatmapsetsKind::Guest),
+            UserList::Favourites => Some(UserBeatmapsetsKind::Favourite),
+            UserList::MostPlayed | UserList::TopPlays => None,
+        }
+    }
+}
+
+impl fmt::Display for UserList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UserList::Ranked => write!(f, "ranked"),
+            UserList::Loved => write!(f, "loved"),
+            UserList::Pending => write!(f, "pending"),
+            UserList::Graveyard => write!(f, "graveyard"),
+            UserList::Guest => write!(f, "guest"),
+            UserList::Favourites => write!(f, "favourite"),
+            UserList::MostPlayed => write!(f, "most played"),
+            UserList::TopPlays => write!(f, "top play"),
+        }
+    }
+}
+
+/// Which lists of a user to collect
+pub struct UserMapsConfig {
+    user: String,
+    lists: Vec<UserList>,
+    game_mode: GameMode,
+    limits: ListingLimits,
+}
+
+impl UserMapsConfig {
+    /// The uploads of `user`, until changed with `lists`
+    pub fn new(user: String) -> Self {
+        Self {
+            user,
+            lists: vec![
+                UserList::Ranked,
+                UserList::Loved,
+                UserList::Pending,
+                UserList::Graveyard,
+                UserList::Guest,
+            ],
+            game_mode: GameMode::Osu,
+            limits: ListingLimits::new(),
+        }
+    }
+
+    #[inline]
+    pub fn lists(mut self, lists: Vec<UserList>) -> Self {
+        self.lists = lists;
+        self
+    }
+
+    /// Game mode of the top plays
+    #[inline]
+    pub const fn game_mode(mut self, game_mode: GameMode) -> Self {
+        self.game_mode = game_mode;
+        self
+    }
+
+    /// When to stop listing, a page being one request of up to 100 entries
+    #[inline]
+    pub fn limits(mut self, limits: ListingLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+/// Mapsets of one page, and whether another page may follow
+async fn fetch_page(
+    osu: &Osu,
+    user_id: u32,
+    list: UserList,
+    game_mode: GameMode,
+    offset: usize,
+) -> Result<(Vec<MapsetInfo>, bool)> {
+    let mapsets: Vec<MapsetInfo> = if let Some(kind) = list.beatmapsets_kind() {
+        osu.user_beatmapsets(user_id, kind)
+            .limit(PAGE_SIZE)
+            .offset(offset)
+            .await
+            .map_err(Error::Search)?
+            .iter()
+            .map(|mapset| MapsetInfo::new(mapset, false))
+            .collect()
+    } else if list == UserList::MostPlayed {
+        osu.user_most_played(user_id)
+            .limit(PAGE_SIZE)
+            .offset(offset)
+            .await
+            .map_err(Error::Search)?
+            .iter()
+            .map(|played| MapsetInfo::from_mapset(&played.mapset, false))
+            .collect()
+    } else {
+        let scores = osu
+            .user_scores(user_id)
+            .best()
+            .mode(game_mode)
+            .limit(PAGE_SIZE)
+            .into_future()
+            .map_err(Error::Search)
+            .await?;
+        // Only the top 100 are wanted, so there is never a next page
+        let mapsets = scores
+            .iter()
+            .filter_map(|score| score.mapset.as_deref())
+            .map(|mapset| MapsetInfo::from_mapset(mapset, false))
+            .collect();
+        return Ok((mapsets, false));
+    };
+    let more = mapsets.len() == PAGE_SIZE;
+    Ok((mapsets, more))
+}
+
+/// Append the mapsets that aren't in `all_mapsets` yet
+///
+/// The lists overlap, a favourite is often among the most played too.
+fn merge(all_mapsets: &mut Vec<MapsetInfo>, seen: &mut HashSet<u32>, page: Vec<MapsetInfo>) {
+    all_mapsets.extend(page.into_iter().filter(|mapset| seen.insert(mapset.mapset_id)));
+}
+
+/// Collect the mapsets on a user's lists, deduplicated across lists
+///
+/// Unlike a text search this finds every set on a list, renamed mapper or not. Fails if the
+/// user is unknown or the first request fails, a later failure ends the listing with what was
+/// collected so far.
+pub async fn user_maps(osu: &Osu, config: UserMapsConfig) -> Result<SearchResult> {
+    let retry_policy = RetryPolicy::new();
+    let user_id = resolve_user(osu, &config.user, &retry_policy).await?.user_id;
+    let owned = owned_or_empty();
+    let mut seen = HashSet::new();
+    let mut all_mapsets: Vec<MapsetInfo> = Vec::new();
+    let mut pages = 0;
+
+    for &list in &config.lists {
+        let mut offset = 0;
+        loop {
+            if pages > 0 && config.limits.reached_limit(all_mapsets.len(), pages) {
+                println!("Stopping the listing after {} requests", pages);
+                return Ok(SearchResult {
+                    mapsets: all_mapsets,
+                    limited: true,
+                    failed_page: None,
+                });
+            }
+            pages += 1;
+            println!("Listing {} maps of {} from {}", list, config.user, offset);
+            let (page, _) = retry(&retry_policy, |_| {
+                fetch_page(osu, user_id, list, config.game_mode, offset)
+            })
+            .await;
+            let (mut page, more) = match page {
+                Ok(page) => page,
+                Err(e) if pages == 1 => return Err(e),
+                Err(e) => return Ok(SearchResult::partial(all_mapsets, pages, e)),
+            };
+            offset += page.len();
+            page.iter_mut().for_each(|mapset| mapset.owned = owned.contains(&mapset.mapset_id));
+            merge(&mut all_mapsets, &mut seen, page);
+            config.limits.record(&mut all_mapsets, pages);
+            if !more {
+                break;
+            }
+        }
+    }
+    println!("Collected {} beatmaps of {}.", all_mapsets.len(), config.user);
+
+    Ok(SearchResult {
+        mapsets: all_mapsets,
+        limited: false,
+        failed_page: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::osu::lists::*;
+    use crate::backend::test_util::mapset;
+
+    #[test]
+    pub fn test_merge() {
+        let mut all_mapsets = Vec::new();
+        let mut seen = HashSet::new();
+        merge(&mut all_mapsets, &mut seen, vec![mapset(1), mapset(2)]);
+        merge(&mut all_mapsets, &mut seen, vec![mapset(2), mapset(3), mapset(3)]);
+        let ids: Vec<u32> = all_mapsets.iter().map(|mapset| mapset.mapset_id).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+    }
+}
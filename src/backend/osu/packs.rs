@@ -0,0 +1,264 @@
+use std::collections::HashSet;
+
+use reqwest::{Client, RequestBuilder, StatusCode, Url};
+use rosu_v2::{prelude::RankStatus, Osu};
+use serde::{de::DeserializeOwned, Deserialize};
+
+use crate::backend::download::{retry, RetryPolicy};
+use crate::backend::error::{Error, Result};
+use crate::backend::osu::{owned_or_empty, ListingLimits, MapsetInfo, SearchResult};
+
+/// rosu-v2 has no beatmap pack endpoints, so they are requested directly
+const PACKS_URL: &str = "https://osu.ppy.sh/api/v2/beatmaps/packs";
+
+/// A range may expand to at most this many packs
+const MAX_RANGE: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackType {
+    Standard,
+    FeaturedArtist,
+    Tournament,
+    Loved,
+    Spotlight,
+    Theme,
+    Artist,
+}
+
+impl PackType {
+    pub const ALL: &'static [PackType] = &[
+        PackType::Standard,
+        PackType::FeaturedArtist,
+        PackType::Tournament,
+        PackType::Loved,
+        PackType::Spotlight,
+        PackType::Theme,
+        PackType::Artist,
+    ];
+
+    /// Name of the type in the API
+    const fn api_name(self) -> &'static str {
+        match self {
+            PackType::Standard => "standard",
+            PackType::FeaturedArtist => "featured",
+            PackType::Tournament => "tournament",
+            PackType::Loved => "loved",
+            PackType::Spotlight => "chart",
+            PackType::Theme => "theme",
+            PackType::Artist => "artist",
+        }
+    }
+
+    /// First letter of the tags of this type, e.g. `S` of `S1234`
+    const fn tag_prefix(self) -> char {
+        match self {
+            PackType::Standard => 'S',
+            PackType::FeaturedArtist => 'F',
+            PackType::Tournament => 'P',
+            PackType::Loved => 'L',
+            PackType::Spotlight => 'R',
+            PackType::Theme => 'T',
+            PackType::Artist => 'A',
+        }
+    }
+}
+
+impl std::fmt::Display for PackType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PackType::Standard => write!(f, "Standard"),
+            PackType::FeaturedArtist => write!(f, "Featured Artist"),
+            PackType::Tournament => write!(f, "Tournament"),
+            PackType::Loved => write!(f, "Project Loved"),
+            PackType::Spotlight => write!(f, "Spotlights"),
+            PackType::Theme => write!(f, "Theme"),
+            PackType::Artist => write!(f, "Artist/Album"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackInfo {
+    pub tag: String,
+    pub name: String,
+}
+
+/// One page of the pack listing, newest first
+#[derive(Debug, Deserialize)]
+pub struct PackPage {
+    #[serde(rename = "beatmap_packs")]
+    pub packs: Vec<PackInfo>,
+    /// Continues the listing with the next older packs
+    #[serde(rename = "cursor_string")]
+    pub cursor: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PackMapset {
+    id: u32,
+    artist: String,
+    title: String,
+    creator: String,
+    status: RankStatus,
+}
+
+#[derive(Deserialize)]
+struct PackDetails {
+    beatmapsets: Vec<PackMapset>,
+}
+
+/// Which packs to expand into their mapsets
+pub struct PacksConfig {
+    tags: Vec<String>,
+    limits: ListingLimits,
+}
+
+impl PacksConfig {
+    pub fn new(tags: Vec<String>) -> Self {
+        Self {
+            tags,
+            limits: ListingLimits::new(),
+        }
+    }
+
+    /// When to stop expanding, a page being one pack
+    #[inline]
+    pub fn limits(mut self, limits: ListingLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+}
+
+/// Split `S1234`, `s1234` or `1234` into its prefix and number
+fn split_tag(tag: &str) -> Option<(Option<char>, u32)> {
+    let prefix = tag.chars().next().filter(char::is_ascii_alphabetic);
+    let number = &tag[prefix.map_or(0, char::len_utf8)..];
+    let number = number.parse().ok()?;
+    Some((prefix.map(|prefix| prefix.to_ascii_uppercase()), number))
+}
+
+/// Parse pack tags and ranges, e.g. `S1400-S1410, F12 T3 R5-8`
+///
+/// The second tag of a range may leave out the prefix. Fails with the offending part.
+pub fn parse_pack_tags(input: &str) -> std::result::Result<Vec<String>, String> {
+    let known = |prefix: char| PackType::ALL.iter().any(|kind| kind.tag_prefix() == prefix);
+    let mut tags = Vec::new();
+    for part in input.split(|c: char| c == ',' || c.is_whitespace()).filter(|part| !part.is_empty()) {
+        let (first, last) = part.split_once('-').unwrap_or((part, part));
+        let (Some((Some(prefix), start)), Some((last_prefix, end))) = (split_tag(first), split_tag(last)) else {
+            return Err(part.to_string());
+        };
+        let same_type = last_prefix.is_none_or(|last_prefix| last_prefix == prefix);
+        if !known(prefix) || !same_type || end < start || end - start >= MAX_RANGE {
+            return Err(part.to_string());
+        }
+        tags.extend((start..=end).map(|number| format!("{}{}", prefix, number)));
+    }
+    Ok(tags)
+}
+
+/// Send an API request with the token of the signed in client
+async fn get_json<T: DeserializeOwned>(osu: &Osu, request: RequestBuilder) -> Result<T> {
+    let token = osu.token();
+    let request = match token.access() {
+        Some(access) => request.bearer_auth(access),
+        None => request,
+    };
+    let res = request.send().await?.error_for_status()?;
+    let body = res.bytes().await?;
+    serde_json::from_slice(&body).map_err(|e| Error::InvalidResponse(e.to_string()))
+}
+
+/// One page of packs of a type, `cursor` continues from a previous page
+pub async fn list_packs(
+    osu: &Osu,
+    client: &Client,
+    pack_type: PackType,
+    cursor: Option<String>,
+) -> Result<PackPage> {
+    let mut params = vec![("type", pack_type.api_name())];
+    if let Some(cursor) = &cursor {
+        params.push(("cursor_string", cursor));
+    }
+    let url = Url::parse_with_params(PACKS_URL, &params).expect("PACKS_URL is a valid URL");
+    let (page, _) = retry(&RetryPolicy::new(), |_| get_json(osu, client.get(url.clone()))).await;
+    page
+}
+
+/// Expand beatmap packs into their mapsets, deduplicated across packs
+///
+/// Tags that don't exist are skipped, ranges often have gaps. Fails if the first pack can't be
+/// looked up, a later failure ends the expansion with what was collected so far.
+pub async fn pack_maps(osu: &Osu, client: &Client, config: PacksConfig) -> Result<SearchResult> {
+    let retry_policy = RetryPolicy::new();
+    let owned = owned_or_empty();
+    let mut seen = HashSet::new();
+    let mut all_mapsets: Vec<MapsetInfo> = Vec::new();
+    let mut pages = 0;
+
+    for tag in &config.tags {
+        if pages > 0 && config.limits.reached_limit(all_mapsets.len(), pages) {
+            println!("Stopping after {} packs", pages);
+            return Ok(SearchResult {
+                mapsets: all_mapsets,
+                limited: true,
+                failed_page: None,
+            });
+        }
+        pages += 1;
+        println!("Looking up beatmap pack {}", tag);
+        let url = format!("{}/{}", PACKS_URL, tag);
+        let (pack, _) = retry(&retry_policy, |_| get_json::<PackDetails>(osu, client.get(&url))).await;
+        let pack = match pack {
+            Ok(pack) => pack,
+            Err(Error::Http { status, .. }) if status == StatusCode::NOT_FOUND => {
+                eprintln!("There is no beatmap pack {}, skipping it", tag);
+                continue;
+            }
+            Err(e) if pages == 1 => return Err(e),
+            Err(e) => return Ok(SearchResult::partial(all_mapsets, pages, e)),
+        };
+        all_mapsets.extend(
+            pack.beatmapsets
+                .into_iter()
+                .filter(|mapset| seen.insert(mapset.id))
+                .map(|mapset| MapsetInfo {
+                    owned: owned.contains(&mapset.id),
+                    mapset_id: mapset.id,
+                    artist: mapset.artist,
+                    title: mapset.title,
+                    creator: mapset.creator,
+                    status: mapset.status,
+                    modes: Vec::new(),
+                    stars: None,
+                }),
+        );
+        config.limits.record(&mut all_mapsets, pages);
+    }
+    println!("Collected {} beatmaps from {} packs.", all_mapsets.len(), pages);
+
+    Ok(SearchResult {
+        mapsets: all_mapsets,
+        limited: false,
+        failed_page: None,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::osu::packs::*;
+
+    #[test]
+    pub fn test_parse_pack_tags() {
+        assert_eq!(
+            parse_pack_tags("S1400-S1402, f12  T3 R5-6").unwrap(),
+            vec!["S1400", "S1401", "S1402", "F12", "T3", "R5", "R6"]
+        );
+        assert!(parse_pack_tags("").unwrap().is_empty());
+        assert_eq!(parse_pack_tags("1234").unwrap_err(), "1234");
+        assert_eq!(parse_pack_tags("X12").unwrap_err(), "X12");
+        assert_eq!(parse_pack_tags("S5-F7").unwrap_err(), "S5-F7");
+        assert_eq!(parse_pack_tags("S9-S1").unwrap_err(), "S9-S1");
+        assert!(parse_pack_tags("S1-S5000").is_err());
+    }
+}
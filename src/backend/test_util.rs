@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+use rosu_v2::prelude::RankStatus;
+
+use crate::backend::osu::MapsetInfo;
+
+/// A ranked set that isn't owned, tests override what they care about
+pub fn mapset(mapset_id: u32) -> MapsetInfo {
+    MapsetInfo {
+        mapset_id,
+        artist: "Artist".to_string(),
+        title: "Title".to_string(),
+        creator: "Mapper".to_string(),
+        status: RankStatus::Ranked,
+        modes: Vec::new(),
+        stars: Some((1.5, 4.2)),
+        owned: false,
+    }
+}
+
+/// Path in the temp directory that is unique to this test run
+pub fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("osu-downloader-{}-{}", std::process::id(), name))
+}
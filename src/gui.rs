@@ -1,38 +1,216 @@
-use std::{sync::Arc, time::Duration};
+use std::{path::PathBuf, sync::Arc, time::Duration};
 
 use iced::{
-    widget::{column, container, text},
-    window, Alignment, Application, Command, Element, Length, Settings, Size, Theme,
+    subscription,
+    widget::{column, container, row, scrollable, text, text_editor},
+    window, Alignment, Application, Command, Element, Length, Settings, Size, Subscription, Theme,
+};
+use reqwest::Client;
+use rosu_v2::{
+    prelude::{GameMode, Genre, Language, RankStatus},
+    Osu,
+};
+use tokio::{
+    sync::{
+        mpsc::{unbounded_channel, UnboundedReceiver},
+        Mutex,
+    },
+    time::sleep,
 };
-use rosu_v2::{prelude::GameMode, Osu};
-use tokio::time::sleep;
 
-use crate::backend::download::download_maps;
-use crate::backend::osu::{login, search_maps, SearchConfig};
+use crate::backend::download::{
+    build_client, clean_stale_parts, discard_unfinished_queue, download_maps, load_unfinished_queue,
+    BandwidthLimit, BatchControl, BeatmapSource, Catboy, ClientConfig,
+    Concurrency, DownloadConfig, Nerinyan, Official, PackageVariant, ProgressEvent, QueueEntry,
+    RetryPolicy, Sayobot, TerminalProgress,
+};
+use crate::backend::error::Error;
+use crate::backend::list_file::{export_list, import_list};
+use crate::backend::osu::{
+    list_packs, login, pack_maps, parse_map_refs, parse_pack_tags, resolve_map_refs, search_maps,
+    user_maps, ListingLimits, MapsetInfo, PackInfo, PackPage, PackType, PacksConfig, ResolvedLinks, SearchConfig,
+    SearchHandle, SearchResult, UserList, UserMapsConfig,
+};
 
 mod components;
+mod filters;
+mod progress;
+mod results;
+
+use filters::{FilterField, FilterInputs, Labeled};
+use progress::BatchProgress;
+use results::SearchResults;
+
+/// How often the live count of a running search is refreshed
+const SEARCH_REFRESH_INTERVAL: Duration = Duration::from_millis(250);
 
 #[derive(Clone)]
 pub enum Message {
+    SourceSelected(SourceOption),
+    UserListToggled(UserListOption, bool),
+    PackTypeSelected(PackType),
+    PackTagsChanged(String),
+    BrowsePacks,
+    MorePacks,
+    PacksListed(Result<Arc<PackPage>, Arc<Error>>),
+    PackPicked(String),
+    LinksEdited(text_editor::Action),
+    LinksFileChanged(String),
+    ImportLinksFile,
+    LinksFileLoaded(Result<String, Arc<Error>>),
+    DownloadLinks,
+    /// Looked up links, together with the pasted tokens that weren't links
+    LinksResolved(Result<Arc<ResolvedLinks>, Arc<Error>>, Vec<String>),
     MapperInputChanged(String),
+    GuestDifficultiesToggled(bool),
     CustomQueryChanged(String),
+    MaxResultsChanged(String),
+    MaxPagesChanged(String),
+    FilterChanged(FilterField, String),
+    StatusFilterSelected(Labeled<Option<RankStatus>>),
+    GenreSelected(Labeled<Option<Genre>>),
+    LanguageSelected(Labeled<Option<Language>>),
+    VideoToggled(bool),
+    StoryboardToggled(bool),
+    MaxAttemptsChanged(String),
     GameModeSelected(GameModeOption),
+    MirrorSelected(MirrorOption),
+    FallbackToggled(bool),
+    PackageSelected(PackageVariant),
+    VerifySetIdToggled(bool),
+    ProxyChanged(String),
+    GlobalLimitChanged(String),
+    PerDownloadLimitChanged(String),
+    ConcurrencyChanged(String),
+    AdaptiveConcurrencyToggled(bool),
+    StartSearch,
+    SearchTick,
+    StopSearch,
+    SearchComplete(Result<Arc<SearchResult>, Arc<Error>>),
+    ResultToggled(usize, bool),
+    SelectAll,
+    SelectNone,
+    InvertSelection,
+    ListFileChanged(String),
+    ExportList,
+    ListExported(Result<usize, Arc<Error>>),
+    ImportList,
+    ListImported(Result<Arc<Vec<MapsetInfo>>, Arc<Error>>),
     StartDownload,
-    DownloadComplete(Result<String, String>),
-    LoginComplete(Result<Arc<Osu>, String>),
+    QueueLoaded(Option<Vec<QueueEntry>>),
+    ResumeQueue,
+    DiscardQueue,
+    PauseBatch,
+    ResumeBatch,
+    CancelBatch,
+    CancelMap(u32),
+    Progress(ProgressEvent),
+    /// Every progress event of the batch with this ID has been delivered
+    ProgressDrained(u64),
+    DownloadComplete(Result<String, Arc<Error>>),
+    LoginComplete(Result<Arc<Osu>, Arc<Error>>),
 }
 
 impl std::fmt::Debug for Message {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::SourceSelected(arg0) => f.debug_tuple("SourceSelected").field(arg0).finish(),
+            Self::UserListToggled(arg0, arg1) => {
+                f.debug_tuple("UserListToggled").field(arg0).field(arg1).finish()
+            }
+            Self::PackTypeSelected(arg0) => f.debug_tuple("PackTypeSelected").field(arg0).finish(),
+            Self::PackTagsChanged(arg0) => f.debug_tuple("PackTagsChanged").field(arg0).finish(),
+            Self::BrowsePacks => write!(f, "BrowsePacks"),
+            Self::MorePacks => write!(f, "MorePacks"),
+            Self::PacksListed(arg0) => f.debug_tuple("PacksListed").field(arg0).finish(),
+            Self::PackPicked(arg0) => f.debug_tuple("PackPicked").field(arg0).finish(),
+            Self::LinksEdited(arg0) => f.debug_tuple("LinksEdited").field(arg0).finish(),
+            Self::LinksFileChanged(arg0) => f.debug_tuple("LinksFileChanged").field(arg0).finish(),
+            Self::ImportLinksFile => write!(f, "ImportLinksFile"),
+            Self::LinksFileLoaded(arg0) => f.debug_tuple("LinksFileLoaded").field(arg0).finish(),
+            Self::DownloadLinks => write!(f, "DownloadLinks"),
+            Self::LinksResolved(arg0, arg1) => {
+                f.debug_tuple("LinksResolved").field(arg0).field(arg1).finish()
+            }
             Self::MapperInputChanged(arg0) => {
                 f.debug_tuple("MapperInputChanged").field(arg0).finish()
             }
+            Self::GuestDifficultiesToggled(arg0) => {
+                f.debug_tuple("GuestDifficultiesToggled").field(arg0).finish()
+            }
             Self::CustomQueryChanged(arg0) => {
                 f.debug_tuple("CustomQueryChanged").field(arg0).finish()
             }
+            Self::MaxResultsChanged(arg0) => {
+                f.debug_tuple("MaxResultsChanged").field(arg0).finish()
+            }
+            Self::MaxPagesChanged(arg0) => f.debug_tuple("MaxPagesChanged").field(arg0).finish(),
+            Self::FilterChanged(arg0, arg1) => {
+                f.debug_tuple("FilterChanged").field(arg0).field(arg1).finish()
+            }
+            Self::StatusFilterSelected(arg0) => {
+                f.debug_tuple("StatusFilterSelected").field(arg0).finish()
+            }
+            Self::GenreSelected(arg0) => f.debug_tuple("GenreSelected").field(arg0).finish(),
+            Self::LanguageSelected(arg0) => {
+                f.debug_tuple("LanguageSelected").field(arg0).finish()
+            }
+            Self::VideoToggled(arg0) => f.debug_tuple("VideoToggled").field(arg0).finish(),
+            Self::StoryboardToggled(arg0) => {
+                f.debug_tuple("StoryboardToggled").field(arg0).finish()
+            }
+            Self::MaxAttemptsChanged(arg0) => {
+                f.debug_tuple("MaxAttemptsChanged").field(arg0).finish()
+            }
             Self::GameModeSelected(arg0) => f.debug_tuple("GameModeSelected").field(arg0).finish(),
+            Self::MirrorSelected(arg0) => f.debug_tuple("MirrorSelected").field(arg0).finish(),
+            Self::FallbackToggled(arg0) => f.debug_tuple("FallbackToggled").field(arg0).finish(),
+            Self::PackageSelected(arg0) => f.debug_tuple("PackageSelected").field(arg0).finish(),
+            Self::VerifySetIdToggled(arg0) => {
+                f.debug_tuple("VerifySetIdToggled").field(arg0).finish()
+            }
+            Self::ProxyChanged(arg0) => f.debug_tuple("ProxyChanged").field(arg0).finish(),
+            Self::GlobalLimitChanged(arg0) => {
+                f.debug_tuple("GlobalLimitChanged").field(arg0).finish()
+            }
+            Self::PerDownloadLimitChanged(arg0) => {
+                f.debug_tuple("PerDownloadLimitChanged").field(arg0).finish()
+            }
+            Self::ConcurrencyChanged(arg0) => {
+                f.debug_tuple("ConcurrencyChanged").field(arg0).finish()
+            }
+            Self::AdaptiveConcurrencyToggled(arg0) => {
+                f.debug_tuple("AdaptiveConcurrencyToggled").field(arg0).finish()
+            }
+            Self::StartSearch => write!(f, "StartSearch"),
+            Self::SearchTick => write!(f, "SearchTick"),
+            Self::StopSearch => write!(f, "StopSearch"),
+            Self::SearchComplete(Ok(result)) => f
+                .debug_tuple("SearchComplete")
+                .field(&format!("Ok({} mapsets)", result.mapsets.len()))
+                .finish(),
+            Self::SearchComplete(Err(e)) => f.debug_tuple("SearchComplete").field(&e).finish(),
+            Self::ResultToggled(arg0, arg1) => {
+                f.debug_tuple("ResultToggled").field(arg0).field(arg1).finish()
+            }
+            Self::SelectAll => write!(f, "SelectAll"),
+            Self::SelectNone => write!(f, "SelectNone"),
+            Self::InvertSelection => write!(f, "InvertSelection"),
+            Self::ListFileChanged(arg0) => f.debug_tuple("ListFileChanged").field(arg0).finish(),
+            Self::ExportList => write!(f, "ExportList"),
+            Self::ListExported(arg0) => f.debug_tuple("ListExported").field(arg0).finish(),
+            Self::ImportList => write!(f, "ImportList"),
+            Self::ListImported(arg0) => f.debug_tuple("ListImported").field(arg0).finish(),
             Self::StartDownload => write!(f, "StartDownload"),
+            Self::QueueLoaded(arg0) => f.debug_tuple("QueueLoaded").field(arg0).finish(),
+            Self::ResumeQueue => write!(f, "ResumeQueue"),
+            Self::DiscardQueue => write!(f, "DiscardQueue"),
+            Self::PauseBatch => write!(f, "PauseBatch"),
+            Self::ResumeBatch => write!(f, "ResumeBatch"),
+            Self::CancelBatch => write!(f, "CancelBatch"),
+            Self::CancelMap(arg0) => f.debug_tuple("CancelMap").field(arg0).finish(),
+            Self::Progress(arg0) => f.debug_tuple("Progress").field(arg0).finish(),
+            Self::ProgressDrained(arg0) => f.debug_tuple("ProgressDrained").field(arg0).finish(),
             Self::DownloadComplete(arg0) => f.debug_tuple("DownloadComplete").field(arg0).finish(),
             Self::LoginComplete(Ok(_)) => f.debug_tuple("LoginComplete").field(&"Ok(Osu)").finish(),
             Self::LoginComplete(Err(e)) => f.debug_tuple("LoginComplete").field(&e).finish(),
@@ -77,13 +255,201 @@ impl std::fmt::Display for GameModeOption {
     }
 }
 
+/// Where the maps to download come from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceOption {
+    Search,
+    UserUploads,
+    PlayerMaps,
+    Packs,
+    Links,
+}
+
+impl SourceOption {
+    const ALL: &'static [SourceOption] = &[
+        SourceOption::Search,
+        SourceOption::UserUploads,
+        SourceOption::PlayerMaps,
+        SourceOption::Packs,
+        SourceOption::Links,
+    ];
+
+    /// Lists to pick from when the maps come from a user
+    fn user_lists(self) -> &'static [UserListOption] {
+        match self {
+            SourceOption::Search | SourceOption::Packs | SourceOption::Links => &[],
+            SourceOption::UserUploads => UserListOption::UPLOADS,
+            SourceOption::PlayerMaps => UserListOption::PLAYER,
+        }
+    }
+}
+
+impl std::fmt::Display for SourceOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SourceOption::Search => write!(f, "Search"),
+            SourceOption::UserUploads => write!(f, "A user's uploads"),
+            SourceOption::PlayerMaps => write!(f, "A player's favourites and plays"),
+            SourceOption::Packs => write!(f, "Beatmap packs"),
+            SourceOption::Links => write!(f, "Links and IDs"),
+        }
+    }
+}
+
+/// A list of a user's maps that can be ticked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserListOption {
+    Ranked,
+    Loved,
+    Pending,
+    Graveyard,
+    Guest,
+    Favourites,
+    MostPlayed,
+    TopPlays,
+}
+
+impl UserListOption {
+    const UPLOADS: &'static [UserListOption] = &[
+        UserListOption::Ranked,
+        UserListOption::Loved,
+        UserListOption::Pending,
+        UserListOption::Graveyard,
+        UserListOption::Guest,
+    ];
+
+    const PLAYER: &'static [UserListOption] = &[
+        UserListOption::Favourites,
+        UserListOption::MostPlayed,
+        UserListOption::TopPlays,
+    ];
+
+    fn to_list(self) -> UserList {
+        match self {
+            UserListOption::Ranked => UserList::Ranked,
+            UserListOption::Loved => UserList::Loved,
+            UserListOption::Pending => UserList::Pending,
+            UserListOption::Graveyard => UserList::Graveyard,
+            UserListOption::Guest => UserList::Guest,
+            UserListOption::Favourites => UserList::Favourites,
+            UserListOption::MostPlayed => UserList::MostPlayed,
+            UserListOption::TopPlays => UserList::TopPlays,
+        }
+    }
+}
+
+impl std::fmt::Display for UserListOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            UserListOption::Ranked => write!(f, "Ranked & approved"),
+            UserListOption::Loved => write!(f, "Loved"),
+            UserListOption::Pending => write!(f, "Pending & WIP"),
+            UserListOption::Graveyard => write!(f, "Graveyard"),
+            UserListOption::Guest => write!(f, "Guest difficulties"),
+            UserListOption::Favourites => write!(f, "Favourites"),
+            UserListOption::MostPlayed => write!(f, "Most played"),
+            UserListOption::TopPlays => write!(f, "Top 100 plays in the selected game mode"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MirrorOption {
+    Sayobot,
+    Official,
+    Nerinyan,
+    Catboy,
+}
+
+impl MirrorOption {
+    const ALL: &'static [MirrorOption] = &[
+        MirrorOption::Sayobot,
+        MirrorOption::Official,
+        MirrorOption::Nerinyan,
+        MirrorOption::Catboy,
+    ];
+
+    /// The selected mirror followed by every other usable mirror as fallback
+    fn fallback_chain(self) -> Vec<Arc<dyn BeatmapSource>> {
+        let mut chain = vec![self.to_source()];
+        chain.extend(
+            Self::ALL
+                .iter()
+                .filter(|&&mirror| mirror != self)
+                .map(|mirror| mirror.to_source())
+                .filter(|source| source.is_available()),
+        );
+        chain
+    }
+
+    fn to_source(self) -> Arc<dyn BeatmapSource> {
+        match self {
+            MirrorOption::Sayobot => Arc::new(Sayobot),
+            MirrorOption::Official => Arc::new(Official),
+            MirrorOption::Nerinyan => Arc::new(Nerinyan),
+            MirrorOption::Catboy => Arc::new(Catboy),
+        }
+    }
+}
+
+impl std::fmt::Display for MirrorOption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_source().name())
+    }
+}
+
 pub struct App {
+    source: SourceOption,
+    user_lists: Vec<UserListOption>,
+    pack_type: PackType,
+    pack_tags_input: String,
+    /// Packs listed by browsing, newest first
+    packs: Vec<PackInfo>,
+    /// Continues the pack listing, `None` once the oldest pack is listed
+    packs_cursor: Option<String>,
+    browsing_packs: bool,
+    /// Pasted or imported beatmap links and IDs
+    links: text_editor::Content,
+    links_file_input: String,
+    resolving_links: bool,
     mapper_input: String,
+    guest_difficulties: bool,
     custom_query: String,
+    max_attempts: String,
     selected_game_mode: Option<GameModeOption>,
+    selected_mirror: Option<MirrorOption>,
+    use_fallback: bool,
+    selected_package: Option<PackageVariant>,
+    verify_set_id: bool,
+    proxy_input: String,
+    global_limit_input: String,
+    per_download_limit_input: String,
+    concurrency_input: String,
+    adaptive_concurrency: bool,
+    max_results_input: String,
+    max_pages_input: String,
+    filters: FilterInputs,
     status_message: String,
     is_downloading: bool,
     osu: Option<Arc<Osu>>,
+    /// Built on first download and reused until the proxy setting changes
+    client: Option<Client>,
+    /// Shared with running batches, so limit changes apply immediately
+    bandwidth: Arc<BandwidthLimit>,
+    /// Batch left unfinished by an earlier session, offered for resuming
+    unfinished_queue: Option<Vec<QueueEntry>>,
+    /// Live count and stop switch of the running search
+    search: Option<Arc<SearchHandle>>,
+    /// Mapsets of the last search, the selected ones get downloaded
+    results: SearchResults,
+    list_file_input: String,
+    /// Pause and cancel switches of the running batch
+    control: Option<Arc<BatchControl>>,
+    /// Progress of the current or last batch
+    progress: BatchProgress,
+    /// Events of the running batch, `batch_id` tells batches apart for the subscription
+    progress_rx: Option<Arc<Mutex<UnboundedReceiver<ProgressEvent>>>>,
+    batch_id: u64,
 }
 
 impl Application for App {
@@ -95,17 +461,54 @@ impl Application for App {
     fn new(_flags: ()) -> (Self, Command<Message>) {
         (
             Self {
+                source: SourceOption::Search,
+                user_lists: [UserListOption::UPLOADS, UserListOption::PLAYER].concat(),
+                pack_type: PackType::Standard,
+                pack_tags_input: String::new(),
+                packs: Vec::new(),
+                packs_cursor: None,
+                browsing_packs: false,
+                links: text_editor::Content::new(),
+                links_file_input: String::new(),
+                resolving_links: false,
                 mapper_input: String::new(),
+                guest_difficulties: false,
                 custom_query: String::new(),
+                max_attempts: String::from("4"),
                 selected_game_mode: Some(GameModeOption::Mania),
+                selected_mirror: Some(MirrorOption::Sayobot),
+                use_fallback: true,
+                selected_package: Some(PackageVariant::Mini),
+                verify_set_id: true,
+                proxy_input: String::new(),
+                global_limit_input: String::new(),
+                per_download_limit_input: String::new(),
+                concurrency_input: String::from("5"),
+                max_results_input: String::new(),
+                max_pages_input: String::new(),
+                filters: FilterInputs::default(),
+                adaptive_concurrency: false,
                 status_message: String::from("Signing in"),
                 is_downloading: false,
                 osu: None,
+                client: None,
+                bandwidth: Arc::new(BandwidthLimit::new()),
+                unfinished_queue: None,
+                search: None,
+                results: SearchResults::default(),
+                list_file_input: String::new(),
+                control: None,
+                progress: BatchProgress::default(),
+                progress_rx: None,
+                batch_id: 0,
             },
-            Command::perform(
-                async { login().await.map(Arc::new).map_err(|e| e.to_string()) },
-                Message::LoginComplete,
-            ),
+            Command::batch([
+                Command::perform(
+                    async { login().await.map(Arc::new).map_err(Arc::new) },
+                    Message::LoginComplete,
+                ),
+                Command::perform(load_unfinished_queue(), Message::QueueLoaded),
+            ]),
         )
     }
 
@@ -118,21 +521,220 @@ impl Application for App {
             Message::MapperInputChanged(input) => {
                 self.mapper_input = input;
             }
+            Message::GuestDifficultiesToggled(guest_difficulties) => {
+                self.guest_difficulties = guest_difficulties;
+            }
             Message::CustomQueryChanged(query) => {
                 self.custom_query = query;
             }
+            Message::MaxAttemptsChanged(input) => {
+                self.max_attempts = input;
+            }
+            Message::SourceSelected(source) => {
+                self.source = source;
+            }
+            Message::UserListToggled(list, enabled) => {
+                self.user_lists.retain(|&l| l != list);
+                if enabled {
+                    self.user_lists.push(list);
+                }
+            }
+            Message::PackTypeSelected(pack_type) => {
+                if pack_type != self.pack_type {
+                    self.pack_type = pack_type;
+                    self.packs.clear();
+                    self.packs_cursor = None;
+                }
+            }
+            Message::PackTagsChanged(input) => {
+                self.pack_tags_input = input;
+            }
+            Message::BrowsePacks => {
+                self.packs.clear();
+                self.packs_cursor = None;
+                return self.browse_packs(None);
+            }
+            Message::MorePacks => {
+                return self.browse_packs(self.packs_cursor.clone());
+            }
+            Message::PacksListed(result) => {
+                self.browsing_packs = false;
+                match result {
+                    Ok(page) => {
+                        self.packs.extend(page.packs.iter().cloned());
+                        self.packs_cursor = page.cursor.clone();
+                        self.status_message = format!("Listed {} {} packs.", self.packs.len(), self.pack_type);
+                    }
+                    Err(e) => self.status_message = describe_error(&e),
+                }
+            }
+            Message::PackPicked(tag) => {
+                let picked = parse_pack_tags(&self.pack_tags_input).unwrap_or_default();
+                if !picked.contains(&tag) {
+                    if !self.pack_tags_input.trim().is_empty() {
+                        self.pack_tags_input = format!("{}, ", self.pack_tags_input.trim_end());
+                    }
+                    self.pack_tags_input.push_str(&tag);
+                }
+            }
+            Message::LinksEdited(action) => {
+                self.links.perform(action);
+            }
+            Message::LinksFileChanged(input) => {
+                self.links_file_input = input;
+            }
+            Message::ImportLinksFile => {
+                let path = self.links_file_input.trim().to_string();
+                if path.is_empty() {
+                    self.status_message = String::from("Error: Please enter the path of the file.");
+                    return Command::none();
+                }
+                return Command::perform(
+                    async move { tokio::fs::read_to_string(path).await.map_err(|e| Arc::new(e.into())) },
+                    Message::LinksFileLoaded,
+                );
+            }
+            Message::LinksFileLoaded(result) => match result {
+                Ok(file) => {
+                    // Add to what was pasted already, a mappool may come in several files
+                    let text = self.links.text();
+                    let text = if text.trim().is_empty() {
+                        file.clone()
+                    } else {
+                        format!("{}\n{}", text.trim_end(), file)
+                    };
+                    self.links = text_editor::Content::with_text(&text);
+                    let (refs, _) = parse_map_refs(&file);
+                    self.status_message = format!("Imported {} links.", refs.len());
+                }
+                Err(e) => self.status_message = format!("Error: Can't read the file: {}", e),
+            },
+            Message::DownloadLinks => {
+                if self.is_downloading || self.resolving_links {
+                    return Command::none();
+                }
+                let Some(osu) = self.osu.clone() else {
+                    self.status_message = String::from("Error: Not signed in yet. Please wait.");
+                    return Command::none();
+                };
+                // Stray words in pasted text don't hold back the links around them
+                let (refs, unknown) = parse_map_refs(&self.links.text());
+                if refs.is_empty() {
+                    self.status_message = if unknown.is_empty() {
+                        String::from("Error: Please paste beatmap links or IDs.")
+                    } else {
+                        format!("Error: Not a beatmap link or ID: {}", unknown.join(", "))
+                    };
+                    return Command::none();
+                }
+                self.resolving_links = true;
+                self.status_message = format!("Looking up {} links...", refs.len());
+                return Command::perform(
+                    async move {
+                        let resolved = resolve_map_refs(&osu, &refs)
+                            .await
+                            .map(Arc::new)
+                            .map_err(Arc::new);
+                        (resolved, unknown)
+                    },
+                    |(resolved, unknown)| Message::LinksResolved(resolved, unknown),
+                );
+            }
+            Message::LinksResolved(result, unparsed) => {
+                self.resolving_links = false;
+                let resolved = match result {
+                    Ok(resolved) => resolved,
+                    Err(e) => {
+                        self.status_message = describe_error(&e);
+                        return Command::none();
+                    }
+                };
+                if resolved.mapset_ids.is_empty() {
+                    self.status_message = describe_error(&Error::NoResults);
+                    return Command::none();
+                }
+                self.status_message = format!("Downloading {} maps...", resolved.mapset_ids.len());
+                if !resolved.unknown_maps.is_empty() {
+                    let unknown: Vec<String> =
+                        resolved.unknown_maps.iter().map(u32::to_string).collect();
+                    self.status_message
+                        .push_str(&format!(" Beatmaps {} weren't found.", unknown.join(", ")));
+                }
+                if !unparsed.is_empty() {
+                    self.status_message
+                        .push_str(&format!(" Skipped {}, not a beatmap link or ID.", unparsed.join(", ")));
+                }
+                return self.start_batch(resolved.mapset_ids.clone());
+            }
             Message::GameModeSelected(mode) => {
                 self.selected_game_mode = Some(mode);
             }
-            Message::StartDownload => {
-                if self.is_downloading {
-                    return Command::none();
+            Message::MirrorSelected(mirror) => {
+                self.selected_mirror = Some(mirror);
+            }
+            Message::FallbackToggled(use_fallback) => {
+                self.use_fallback = use_fallback;
+            }
+            Message::PackageSelected(package) => {
+                self.selected_package = Some(package);
+            }
+            Message::VerifySetIdToggled(verify_set_id) => {
+                self.verify_set_id = verify_set_id;
+            }
+            Message::ProxyChanged(proxy) => {
+                self.proxy_input = proxy;
+                self.client = None;
+            }
+            Message::GlobalLimitChanged(input) => {
+                match parse_rate(&input) {
+                    Some(rate) => self.bandwidth.set_global(rate),
+                    None => {
+                        self.status_message = String::from("Error: Speed limits must be numbers in KiB/s.")
+                    }
                 }
-
-                if self.mapper_input.trim().is_empty() && self.custom_query.trim().is_empty() {
-                    self.status_message = String::from(
-                        "Error: Please specity at least one condition, mapper or custom query.",
-                    );
+                self.global_limit_input = input;
+            }
+            Message::PerDownloadLimitChanged(input) => {
+                match parse_rate(&input) {
+                    Some(rate) => self.bandwidth.set_per_download(rate),
+                    None => {
+                        self.status_message = String::from("Error: Speed limits must be numbers in KiB/s.")
+                    }
+                }
+                self.per_download_limit_input = input;
+            }
+            Message::MaxResultsChanged(input) => {
+                self.max_results_input = input;
+            }
+            Message::MaxPagesChanged(input) => {
+                self.max_pages_input = input;
+            }
+            Message::FilterChanged(field, input) => {
+                self.filters.set(field, input);
+            }
+            Message::StatusFilterSelected(status) => {
+                self.filters.status = status;
+            }
+            Message::GenreSelected(genre) => {
+                self.filters.genre = genre;
+            }
+            Message::LanguageSelected(language) => {
+                self.filters.language = language;
+            }
+            Message::VideoToggled(video) => {
+                self.filters.video = video;
+            }
+            Message::StoryboardToggled(storyboard) => {
+                self.filters.storyboard = storyboard;
+            }
+            Message::ConcurrencyChanged(input) => {
+                self.concurrency_input = input;
+            }
+            Message::AdaptiveConcurrencyToggled(adaptive) => {
+                self.adaptive_concurrency = adaptive;
+            }
+            Message::StartSearch => {
+                if self.is_downloading || self.search.is_some() {
                     return Command::none();
                 }
 
@@ -141,35 +743,195 @@ impl Application for App {
                     return Command::none();
                 }
 
-                if self.selected_game_mode.is_none() {
-                    self.status_message = String::from("Error: Please select a game mode");
+                let (Some(max_results), Some(max_pages)) =
+                    (parse_limit(&self.max_results_input), parse_limit(&self.max_pages_input))
+                else {
+                    self.status_message =
+                        String::from("Error: Search limits must be positive numbers or empty.");
                     return Command::none();
-                }
-
-                self.is_downloading = true;
-                self.status_message = String::from("Downloading...");
-
-                let mapper = self.mapper_input.clone();
-                let custom_query = self.custom_query.clone();
-                let game_mode = self.selected_game_mode.unwrap().to_game_mode();
-                let osu_clone = Arc::clone(&self.osu.as_ref().unwrap());
+                };
 
+                return match self.source {
+                    SourceOption::Search => self.start_search(max_results, max_pages),
+                    SourceOption::UserUploads | SourceOption::PlayerMaps => {
+                        self.start_user_maps(max_results, max_pages)
+                    }
+                    SourceOption::Packs => self.start_packs(max_results, max_pages),
+                    // Links aren't searched, they have their own download button
+                    SourceOption::Links => Command::none(),
+                };
+            }
+            // Only redraws, the search panel reads the live counts from the handle
+            Message::SearchTick => {}
+            Message::SearchComplete(result) => {
+                self.search = None;
+                match result {
+                    Ok(search) => {
+                        self.results = SearchResults::new(search.mapsets.clone());
+                        self.status_message = describe_search(&search, self.results.owned_count());
+                    }
+                    Err(e) => {
+                        self.results = SearchResults::default();
+                        self.status_message = describe_error(&e);
+                    }
+                }
+            }
+            Message::ResultToggled(index, selected) => {
+                self.results.set_selected(index, selected);
+            }
+            Message::SelectAll => self.results.select_all(),
+            Message::SelectNone => self.results.select_none(),
+            Message::InvertSelection => self.results.invert(),
+            Message::ListFileChanged(input) => {
+                self.list_file_input = input;
+            }
+            Message::ExportList => {
+                let mapsets = self.results.selected_mapsets();
+                if mapsets.is_empty() {
+                    self.status_message = String::from("Error: Select the maps to export first.");
+                    return Command::none();
+                }
+                let Some(path) = self.list_file_path() else {
+                    return Command::none();
+                };
+                return Command::perform(
+                    async move {
+                        tokio::task::spawn_blocking(move || export_list(&path, &mapsets).map(|_| mapsets.len()))
+                            .await
+                            .map_err(|e| Arc::new(std::io::Error::from(e).into()))?
+                            .map_err(Arc::new)
+                    },
+                    Message::ListExported,
+                );
+            }
+            Message::ListExported(result) => match result {
+                Ok(count) => {
+                    self.status_message =
+                        format!("Exported {} sets to {}.", count, self.list_file_input.trim())
+                }
+                Err(e) => self.status_message = describe_error(&e),
+            },
+            Message::ImportList => {
+                if self.search.is_some() {
+                    return Command::none();
+                }
+                let Some(path) = self.list_file_path() else {
+                    return Command::none();
+                };
                 return Command::perform(
-                    async move { download_task(osu_clone, mapper, custom_query, game_mode).await },
-                    |result| Message::DownloadComplete(result),
+                    async move {
+                        tokio::task::spawn_blocking(move || import_list(&path))
+                            .await
+                            .map_err(|e| Arc::new(std::io::Error::from(e).into()))?
+                            .map(Arc::new)
+                            .map_err(Arc::new)
+                    },
+                    Message::ListImported,
                 );
             }
+            Message::ListImported(result) => match result {
+                Ok(mapsets) => {
+                    // An imported list takes the place of the results, ready to be downloaded
+                    self.results = SearchResults::new(mapsets.to_vec());
+                    self.status_message = format!(
+                        "Imported {} sets, {} already owned. Pick the ones to download.",
+                        mapsets.len(),
+                        self.results.owned_count()
+                    );
+                }
+                Err(e) => self.status_message = describe_error(&e),
+            },
+            Message::StartDownload => {
+                if self.is_downloading || self.search.is_some() {
+                    return Command::none();
+                }
+                let mapset_ids = self.results.selected_ids();
+                if mapset_ids.is_empty() {
+                    self.status_message = String::from("Error: Search first and select the maps to download.");
+                    return Command::none();
+                }
+                self.status_message = format!("Downloading {} maps...", mapset_ids.len());
+                return self.start_batch(mapset_ids);
+            }
+            Message::StopSearch => {
+                if let Some(handle) = &self.search {
+                    handle.stop();
+                }
+            }
+            Message::QueueLoaded(unfinished) => {
+                self.unfinished_queue = unfinished;
+            }
+            Message::ResumeQueue => {
+                if self.is_downloading {
+                    return Command::none();
+                }
+                let Some(unfinished) = self.unfinished_queue.take() else {
+                    return Command::none();
+                };
+                let mapset_ids: Vec<u32> = unfinished.iter().map(|entry| entry.mapset_id).collect();
+                self.status_message = format!("Resuming {} maps...", mapset_ids.len());
+                let command = self.start_batch(mapset_ids);
+                // Keep offering the batch if it couldn't be started
+                if !self.is_downloading {
+                    self.unfinished_queue = Some(unfinished);
+                }
+                return command;
+            }
+            Message::DiscardQueue => {
+                self.unfinished_queue = None;
+                return Command::perform(
+                    async {
+                        if let Err(e) = discard_unfinished_queue().await {
+                            eprintln!("Failed to remove download queue: {}", e);
+                        }
+                    },
+                    |_| Message::QueueLoaded(None),
+                );
+            }
+            Message::PauseBatch => {
+                if let Some(control) = &self.control {
+                    control.pause();
+                    self.status_message = String::from("Paused.");
+                }
+            }
+            Message::ResumeBatch => {
+                if let Some(control) = &self.control {
+                    control.resume();
+                    self.status_message = String::from("Downloading...");
+                }
+            }
+            Message::CancelBatch => {
+                if let Some(control) = &self.control {
+                    control.cancel();
+                    self.status_message = String::from("Cancelling...");
+                }
+            }
+            Message::CancelMap(map_id) => {
+                if let Some(control) = &self.control {
+                    control.cancel_map(map_id);
+                }
+            }
+            Message::Progress(event) => {
+                self.progress.apply(event);
+            }
+            Message::ProgressDrained(batch_id) => {
+                if batch_id == self.batch_id {
+                    self.progress_rx = None;
+                }
+            }
             Message::DownloadComplete(result) => {
                 self.is_downloading = false;
+                self.control = None;
                 match result {
                     Ok(download_msg) => {
-                        self.status_message =
-                            String::from(format!("Download finished! {}", download_msg));
+                        self.status_message = format!("Download finished! {}", download_msg);
                     }
                     Err(e) => {
-                        self.status_message = format!("Download failed: {}", e);
+                        self.status_message = describe_error(&e);
                     }
                 }
+                // Offer the maps that failed or were cancelled for another try
+                return Command::perform(load_unfinished_queue(), Message::QueueLoaded);
             }
             Message::LoginComplete(result) => match result {
                 Ok(osu) => {
@@ -177,11 +939,11 @@ impl Application for App {
                     self.status_message = String::from("Signed in successfully");
                 }
                 Err(e) => {
-                    self.status_message = format!("Login failed: {}, retry in 3 seconds...", e);
+                    self.status_message = format!("{}, retrying in 3 seconds...", describe_error(&e));
                     return Command::perform(
                         async {
                             sleep(Duration::from_secs(3)).await;
-                            login().await.map(Arc::new).map_err(|e| e.to_string())
+                            login().await.map(Arc::new).map_err(Arc::new)
                         },
                         Message::LoginComplete,
                     );
@@ -192,30 +954,176 @@ impl Application for App {
         Command::none()
     }
 
+    fn subscription(&self) -> Subscription<Message> {
+        let progress = match &self.progress_rx {
+            // Keeps forwarding after the batch returned, until the last event is through
+            Some(rx) => {
+                let batch_id = self.batch_id;
+                subscription::unfold(batch_id, Some(Arc::clone(rx)), move |rx| async move {
+                    let Some(rx) = rx else {
+                        return std::future::pending().await;
+                    };
+                    let event = rx.lock().await.recv().await;
+                    match event {
+                        Some(event) => (Message::Progress(event), Some(rx)),
+                        // The batch dropped its sender, every event has been forwarded
+                        None => (Message::ProgressDrained(batch_id), None),
+                    }
+                })
+            }
+            None => Subscription::none(),
+        };
+        // The search count lives in the handle, look at it a few times a second
+        let search = if self.search.is_some() {
+            iced::time::every(SEARCH_REFRESH_INTERVAL).map(|_| Message::SearchTick)
+        } else {
+            Subscription::none()
+        };
+        Subscription::batch([progress, search])
+    }
+
     fn theme(&self) -> Theme {
         Theme::Dark
     }
 
     fn view(&self) -> Element<'_, Message> {
         let mapper_input = components::mapper_input(&self.mapper_input);
+        let guest_difficulties_checkbox =
+            components::guest_difficulties_checkbox(self.guest_difficulties);
         let custom_query_input = components::custom_query_input(&self.custom_query);
         let game_mode_pick = components::game_mode_pick(self.selected_game_mode);
-        let download_button = components::download_button(self.is_downloading);
+        let max_attempts_input = components::max_attempts_input(&self.max_attempts);
+        let mirror_pick = components::mirror_pick(self.selected_mirror);
+        let mirror_capabilities = components::mirror_capabilities(self.selected_mirror);
+        let fallback_checkbox = components::fallback_checkbox(self.use_fallback);
+        let package_pick = components::package_pick(self.selected_package);
+        let verify_set_id_checkbox = components::verify_set_id_checkbox(self.verify_set_id);
+        let proxy_input = components::proxy_input(&self.proxy_input);
+        let global_limit_input = components::global_limit_input(&self.global_limit_input);
+        let per_download_limit_input =
+            components::per_download_limit_input(&self.per_download_limit_input);
+        let concurrency_input = components::concurrency_input(&self.concurrency_input);
+        let adaptive_concurrency_checkbox =
+            components::adaptive_concurrency_checkbox(self.adaptive_concurrency);
+        let max_results_input = components::max_results_input(&self.max_results_input);
+        let max_pages_input = components::max_pages_input(&self.max_pages_input);
+        let filters_form = components::filters_form(
+            &self.filters,
+            self.selected_game_mode == Some(GameModeOption::Mania),
+        );
+        let search_panel = components::search_panel(self.search.as_deref().map(SearchHandle::counts));
+        let resume_panel = components::resume_panel(self.unfinished_queue.as_deref(), self.is_downloading);
+        let batch_controls =
+            components::batch_controls(self.control.as_deref().map(BatchControl::is_paused));
+        let progress_panel = components::progress_panel(&self.progress, self.control.is_some());
+        let results_panel = components::results_panel(&self.results);
+        // Links skip the search, they are looked up and downloaded right away
+        let action_buttons = if self.source == SourceOption::Links {
+            row![components::download_links_button(self.is_downloading || self.resolving_links)]
+        } else {
+            row![
+                components::search_button(self.is_downloading || self.search.is_some()),
+                components::download_button(self.is_downloading, self.results.selected_ids().len()),
+            ]
+        };
         let status_text = components::status_text(&self.status_message);
-        let content = column![
+        let source_pick = components::source_pick(self.source);
+        let source_form: Element<'_, Message> = match self.source {
+            SourceOption::Search => column![
+                text("Mapper name:").size(14),
+                mapper_input,
+                guest_difficulties_checkbox,
+                text("Game mode:").size(14),
+                game_mode_pick,
+                filters_form,
+                text("Custom query (optional, for terms the filters don't cover):").size(14),
+                custom_query_input,
+            ]
+            .spacing(15)
+            .into(),
+            SourceOption::UserUploads => column![
+                text("User name:").size(14),
+                mapper_input,
+                components::user_lists(self.source.user_lists(), &self.user_lists),
+            ]
+            .spacing(15)
+            .into(),
+            SourceOption::Packs => column![
+                text("Pack type:").size(14),
+                components::pack_type_pick(self.pack_type),
+                components::packs_browser(&self.packs, self.packs_cursor.is_some(), self.browsing_packs),
+                text("Pack tags or ranges (e.g. S1400-S1410, F12, T3):").size(14),
+                components::pack_tags_input(&self.pack_tags_input),
+            ]
+            .spacing(15)
+            .into(),
+            SourceOption::Links => column![
+                text("Beatmap links or mapset IDs, one per line:").size(14),
+                components::links_editor(&self.links),
+                components::links_file_import(&self.links_file_input),
+            ]
+            .spacing(15)
+            .into(),
+            SourceOption::PlayerMaps => column![
+                text("Player name:").size(14),
+                mapper_input,
+                text("Game mode:").size(14),
+                game_mode_pick,
+                components::user_lists(self.source.user_lists(), &self.user_lists),
+            ]
+            .spacing(15)
+            .into(),
+        };
+        let form = column![
             text("Osu! Beatmap Downloader").size(24),
-            text("Mapper name:").size(14),
-            mapper_input,
-            text("Custom query (optional):").size(14),
-            custom_query_input,
-            text("Game mode:").size(14),
-            game_mode_pick,
-            download_button,
-            status_text,
+            progress_panel,
+            text("Find maps by:").size(14),
+            source_pick,
+            source_form,
+            text("Max results (optional):").size(14),
+            max_results_input,
+            text("Max result pages (optional):").size(14),
+            max_pages_input,
+            results_panel,
+            components::list_file_panel(&self.list_file_input, self.results.selected_ids().len()),
+            text("Download mirror:").size(14),
+            mirror_pick,
+            mirror_capabilities,
+            fallback_checkbox,
+            text("Package:").size(14),
+            package_pick,
+            verify_set_id_checkbox,
+            text("Proxy (optional):").size(14),
+            proxy_input,
+            text("Total speed limit in KiB/s (optional):").size(14),
+            global_limit_input,
+            text("Speed limit per download in KiB/s (optional):").size(14),
+            per_download_limit_input,
+            text("Concurrent downloads:").size(14),
+            concurrency_input,
+            adaptive_concurrency_checkbox,
+            text("Attempts per map:").size(14),
+            max_attempts_input,
         ]
         .spacing(15)
         .padding(20)
         .align_items(Alignment::Start)
+        .width(Length::Fill);
+
+        // Progress and settings scroll, the download button and status always stay in view
+        let content = column![
+            scrollable(form).height(Length::Fill),
+            column![
+                resume_panel,
+                search_panel,
+                batch_controls,
+                action_buttons.spacing(10),
+                status_text
+            ]
+                .spacing(15)
+                .padding([0, 20]),
+        ]
+        .spacing(15)
         .width(Length::Fill)
         .height(Length::Fill);
 
@@ -227,34 +1135,360 @@ impl Application for App {
     }
 }
 
-async fn download_task(
-    osu: Arc<Osu>,
-    mapper: String,
-    custom_query: String,
-    game_mode: GameMode,
-) -> Result<String, String> {
-    let search_config = SearchConfig::new()
-        .game_mode(game_mode)
-        .mapper(mapper.trim().to_string())
-        .custom_query(custom_query.trim().to_string());
+impl App {
+    /// Limits of a search or listing about to start, with a fresh handle the window watches
+    fn watch_listing(&mut self, max_results: Option<usize>, max_pages: Option<usize>) -> ListingLimits {
+        let handle = Arc::new(SearchHandle::new());
+        self.search = Some(Arc::clone(&handle));
+        let mut limits = ListingLimits::new().handle(handle);
+        if let Some(max_results) = max_results {
+            limits = limits.max_results(max_results);
+        }
+        if let Some(max_pages) = max_pages {
+            limits = limits.max_pages(max_pages);
+        }
+        limits
+    }
 
-    let mapset_ids = search_maps(&osu, search_config).await;
+    /// Run a text search with the typed filters
+    fn start_search(&mut self, max_results: Option<usize>, max_pages: Option<usize>) -> Command<Message> {
+        let Some(game_mode) = self.selected_game_mode else {
+            self.status_message = String::from("Error: Please select a game mode");
+            return Command::none();
+        };
+
+        let filters = match self.filters.to_filters(game_mode == GameModeOption::Mania) {
+            Ok(filters) => filters,
+            Err(e) => {
+                self.status_message = e;
+                return Command::none();
+            }
+        };
+
+        if self.mapper_input.trim().is_empty()
+            && self.custom_query.trim().is_empty()
+            && filters.query().is_empty()
+        {
+            self.status_message = String::from(
+                "Error: Please specity at least one condition, mapper, filter or custom query.",
+            );
+            return Command::none();
+        }
 
-    if mapset_ids.is_empty() {
-        return Err(format!("No beatmaps found."));
+        self.status_message = String::from("Searching...");
+
+        let mut search_config = SearchConfig::new()
+            .game_mode(game_mode.to_game_mode())
+            .custom_query(self.custom_query.trim().to_string())
+            .filters(filters)
+            .limits(self.watch_listing(max_results, max_pages));
+        if !self.mapper_input.trim().is_empty() {
+            search_config = search_config
+                .mapper(self.mapper_input.trim().to_string())
+                .guest_difficulties(self.guest_difficulties);
+        }
+        let osu_clone = Arc::clone(self.osu.as_ref().unwrap());
+
+        Command::perform(
+            async move {
+                search_maps(&osu_clone, search_config)
+                    .await
+                    .map(Arc::new)
+                    .map_err(Arc::new)
+            },
+            Message::SearchComplete,
+        )
+    }
+
+    /// Collect the ticked lists of a user
+    fn start_user_maps(&mut self, max_results: Option<usize>, max_pages: Option<usize>) -> Command<Message> {
+        if self.mapper_input.trim().is_empty() {
+            self.status_message = String::from("Error: Please enter the user name.");
+            return Command::none();
+        }
+
+        // Keep the lists in the order they are shown
+        let lists: Vec<UserList> = self
+            .source
+            .user_lists()
+            .iter()
+            .filter(|list| self.user_lists.contains(list))
+            .map(|list| list.to_list())
+            .collect();
+        if lists.is_empty() {
+            self.status_message = String::from("Error: Please tick at least one list.");
+            return Command::none();
+        }
+
+        let Some(game_mode) = self.selected_game_mode else {
+            self.status_message = String::from("Error: Please select a game mode");
+            return Command::none();
+        };
+
+        self.status_message = String::from("Listing beatmapsets...");
+
+        let user_maps_config = UserMapsConfig::new(self.mapper_input.trim().to_string())
+            .lists(lists)
+            .game_mode(game_mode.to_game_mode())
+            .limits(self.watch_listing(max_results, max_pages));
+        let osu_clone = Arc::clone(self.osu.as_ref().unwrap());
+
+        Command::perform(
+            async move {
+                user_maps(&osu_clone, user_maps_config)
+                    .await
+                    .map(Arc::new)
+                    .map_err(Arc::new)
+            },
+            Message::SearchComplete,
+        )
+    }
+
+    /// Download `mapset_ids` as a new batch, or report why it can't start
+    ///
+    /// A new batch replaces the queue file, so an unfinished batch has to be resumed or discarded first.
+    fn start_batch(&mut self, mapset_ids: Vec<u32>) -> Command<Message> {
+        if self.unfinished_queue.is_some() {
+            self.status_message =
+                String::from("Error: Resume or discard the unfinished batch before starting a new one.");
+            return Command::none();
+        }
+        let (client, download_config) = match self.prepare_batch() {
+            Ok(settings) => settings,
+            Err(e) => {
+                self.status_message = e;
+                return Command::none();
+            }
+        };
+        self.is_downloading = true;
+
+        Command::perform(
+            async move {
+                download_maps(client, mapset_ids, download_config)
+                    .await
+                    .map_err(Arc::new)
+            },
+            Message::DownloadComplete,
+        )
+    }
+
+    /// List a page of packs of the selected type
+    fn browse_packs(&mut self, cursor: Option<String>) -> Command<Message> {
+        if self.browsing_packs {
+            return Command::none();
+        }
+        let Some(osu) = self.osu.clone() else {
+            self.status_message = String::from("Error: Not signed in yet. Please wait.");
+            return Command::none();
+        };
+        let client = match self.http_client() {
+            Ok(client) => client,
+            Err(e) => {
+                self.status_message = e;
+                return Command::none();
+            }
+        };
+        self.browsing_packs = true;
+        let pack_type = self.pack_type;
+
+        Command::perform(
+            async move {
+                list_packs(&osu, &client, pack_type, cursor)
+                    .await
+                    .map(Arc::new)
+                    .map_err(Arc::new)
+            },
+            Message::PacksListed,
+        )
+    }
+
+    /// Expand the entered packs into their mapsets
+    fn start_packs(&mut self, max_results: Option<usize>, max_pages: Option<usize>) -> Command<Message> {
+        let tags = match parse_pack_tags(&self.pack_tags_input) {
+            Ok(tags) if tags.is_empty() => {
+                self.status_message =
+                    String::from("Error: Please enter pack tags or add packs from the list.");
+                return Command::none();
+            }
+            Ok(tags) => tags,
+            Err(part) => {
+                self.status_message = format!("Error: {} is not a pack tag or range.", part);
+                return Command::none();
+            }
+        };
+        let client = match self.http_client() {
+            Ok(client) => client,
+            Err(e) => {
+                self.status_message = e;
+                return Command::none();
+            }
+        };
+
+        self.status_message = format!("Looking up {} packs...", tags.len());
+
+        let packs_config = PacksConfig::new(tags).limits(self.watch_listing(max_results, max_pages));
+        let osu_clone = Arc::clone(self.osu.as_ref().unwrap());
+
+        Command::perform(
+            async move {
+                pack_maps(&osu_clone, &client, packs_config)
+                    .await
+                    .map(Arc::new)
+                    .map_err(Arc::new)
+            },
+            Message::SearchComplete,
+        )
+    }
+
+    /// Path of the list file to export to or import from
+    fn list_file_path(&mut self) -> Option<PathBuf> {
+        let path = self.list_file_input.trim();
+        if path.is_empty() {
+            self.status_message = String::from("Error: Please enter the path of a .csv or .json file.");
+            return None;
+        }
+        Some(PathBuf::from(path))
+    }
+
+    /// The HTTP client for mirrors and raw API requests, built on first use
+    fn http_client(&mut self) -> Result<Client, String> {
+        if self.client.is_none() {
+            let mut client_config = ClientConfig::new();
+            if !self.proxy_input.trim().is_empty() {
+                client_config = client_config.proxy(self.proxy_input.trim().to_string());
+            }
+            let client = build_client(&client_config)
+                .map_err(|e| format!("Error: Invalid proxy setting: {}", e))?;
+            self.client = Some(client);
+        }
+        Ok(self.client.clone().unwrap())
     }
 
-    let download_msg = download_maps(mapset_ids)
-        .await
-        .map_err(|e| format!("Download failed: {}", e))?;
+    /// Collect the download settings of the form and set up control and progress tracking of a new batch
+    fn prepare_batch(&mut self) -> Result<(Client, DownloadConfig), String> {
+        let max_attempts = match self.max_attempts.trim().parse::<u32>() {
+            Ok(n) if n >= 1 => n,
+            _ => return Err(String::from("Error: Attempts per map must be a positive number.")),
+        };
+
+        let concurrency = match self.concurrency_input.trim().parse::<usize>() {
+            Ok(n) if n >= 1 && self.adaptive_concurrency => Concurrency::Adaptive { max: n },
+            Ok(n) if n >= 1 => Concurrency::Fixed(n),
+            _ => return Err(String::from("Error: Concurrent downloads must be a positive number.")),
+        };
+
+        let mirror = self.selected_mirror.unwrap_or(MirrorOption::Sayobot);
+        let package = self.selected_package.unwrap_or(PackageVariant::Mini);
+        if !self.use_fallback && !mirror.to_source().capabilities().supports(package) {
+            return Err(format!("Error: {} doesn't serve {} packages.", mirror, package));
+        }
+        let sources = if self.use_fallback {
+            mirror.fallback_chain()
+        } else {
+            vec![mirror.to_source()]
+        };
+
+        let client = self.http_client()?;
+
+        let download_config = DownloadConfig::new()
+            .retry_policy(RetryPolicy::new().max_attempts(max_attempts))
+            .sources(sources)
+            .variant(package)
+            .verify_set_id(self.verify_set_id)
+            .bandwidth(Arc::clone(&self.bandwidth))
+            .concurrency(concurrency);
+
+        // Each batch gets fresh switches, so cancelling one doesn't carry over to the next
+        let control = Arc::new(BatchControl::new());
+        self.control = Some(Arc::clone(&control));
+        let (progress_tx, progress_rx) = unbounded_channel();
+        self.progress = BatchProgress::default();
+        self.progress_rx = Some(Arc::new(Mutex::new(progress_rx)));
+        self.batch_id += 1;
+        let download_config = download_config
+            .control(control)
+            .progress(vec![Arc::new(TerminalProgress::new()), Arc::new(progress_tx)]);
 
-    Ok(download_msg)
+        Ok((client, download_config))
+    }
+}
+
+/// Parse an optional search limit, `None` if it isn't a positive number
+fn parse_limit(input: &str) -> Option<Option<usize>> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(None);
+    }
+    input.parse::<usize>().ok().filter(|&n| n >= 1).map(Some)
+}
+
+/// Parse a speed limit in KiB/s into bytes per second, empty meaning unlimited (0)
+fn parse_rate(input: &str) -> Option<u64> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Some(0);
+    }
+    input.parse::<u64>().ok().and_then(|kib| kib.checked_mul(1024))
+}
+
+/// Status line for a failure, with a hint on what to do about it
+fn describe_error(e: &Error) -> String {
+    let hint = match e {
+        Error::Network(_) => "Check your internet connection and proxy setting.",
+        Error::Http { status, .. } if status.as_u16() == 401 || status.as_u16() == 403 => {
+            "The mirror refused access. The official mirror needs OSU_SESSION to be set."
+        }
+        Error::Http { status, .. } if status.as_u16() == 404 => "The map isn't available there, try another mirror.",
+        Error::Http { status, .. } if status.as_u16() == 429 => {
+            "The mirror is rate limiting, lower the concurrent downloads."
+        }
+        Error::Http { .. } => "The mirror is having trouble, try another one or enable fallback.",
+        Error::Io(_) => "Check that ./Songs is writable and the disk isn't full.",
+        Error::InvalidResponse(_) | Error::InvalidArchive(_) => {
+            "The mirror served a broken file, try another mirror."
+        }
+        Error::Auth(_) => "Check your internet connection.",
+        Error::Search(_) => "Check the custom query or try again later.",
+        Error::UnknownUser(_) => "Check the spelling of the mapper name.",
+        Error::NoResults => "Check the mapper name or loosen the query.",
+        Error::GameDir { .. } => "Set OSU_PATH to your osu! installation.",
+        Error::InvalidList(_) => "Use a .csv or .json file exported by this app.",
+        Error::NoMirror(_) => "Pick another package or mirror, or enable fallback.",
+        Error::MirrorPaused { .. } => "Enable fallback to other mirrors or try again later.",
+        Error::Cancelled => return String::from("Download cancelled."),
+    };
+    format!("Error: {}. {}", e, hint)
+}
+
+/// Status line for a finished search
+fn describe_search(search: &SearchResult, owned: usize) -> String {
+    let found = search.mapsets.len();
+    if found == 0 {
+        return match &search.failed_page {
+            Some((_, e)) => describe_error(e),
+            None => describe_error(&Error::NoResults),
+        };
+    }
+    let stopped = match &search.failed_page {
+        Some((page, e)) => format!("Search stopped early, page {} failed ({}). ", page, e),
+        None if search.limited => String::from("Search stopped at the limit. "),
+        None => String::new(),
+    };
+    format!(
+        "{}Found {} sets, {} already owned. Pick the ones to download.",
+        stopped, found, owned
+    )
 }
 
 pub fn run() -> iced::Result {
+    let removed = clean_stale_parts();
+    if removed > 0 {
+        println!("Removed {} abandoned partial downloads.", removed);
+    }
+
     App::run(Settings {
         window: window::Settings {
-            size: Size::new(480.0, 520.0),
+            size: Size::new(480.0, 640.0),
             min_size: Some(Size::new(480.0, 480.0)),
             ..Default::default()
         },
@@ -1,7 +1,19 @@
 use iced::{Element, Length};
-use iced::widget::{button, pick_list, text, text_input};
+use iced::widget::{
+    button, checkbox, column, pick_list, progress_bar, row, scrollable, text, text_editor, text_input,
+};
 
-use super::{GameModeOption, Message};
+use super::filters::{FilterField, FilterInputs, GENRES, LANGUAGES, STATUSES};
+use super::progress::{BatchProgress, MapState};
+use super::results::SearchResults;
+use super::{GameModeOption, Message, MirrorOption, SourceOption, UserListOption};
+use crate::backend::download::{PackageVariant, QueueEntry};
+use crate::backend::osu::{PackInfo, PackType};
+
+/// Height of the search results list, which scrolls on its own
+const RESULTS_HEIGHT: f32 = 300.0;
+/// Height of the per-map progress rows, which scroll on their own
+const PROGRESS_HEIGHT: f32 = 250.0;
 
 pub fn mapper_input<'a>(value: &'a str) -> Element<'a, Message> {
     text_input("Enter mapper name", value)
@@ -11,6 +23,12 @@ pub fn mapper_input<'a>(value: &'a str) -> Element<'a, Message> {
         .into()
 }
 
+pub fn guest_difficulties_checkbox(guest_difficulties: bool) -> Element<'static, Message> {
+    checkbox("Include guest difficulties on other mappers' sets", guest_difficulties)
+        .on_toggle(Message::GuestDifficultiesToggled)
+        .into()
+}
+
 pub fn custom_query_input<'a>(value: &'a str) -> Element<'a, Message> {
     text_input("Enter custom query (e.g. key=7 status=r)", value)
         .on_input(Message::CustomQueryChanged)
@@ -19,6 +37,132 @@ pub fn custom_query_input<'a>(value: &'a str) -> Element<'a, Message> {
         .into()
 }
 
+pub fn max_results_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Stop searching after this many sets", value)
+        .on_input(Message::MaxResultsChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+pub fn max_pages_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Stop searching after this many pages", value)
+        .on_input(Message::MaxPagesChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+fn filter_input<'a>(placeholder: &str, field: FilterField, inputs: &'a FilterInputs) -> Element<'a, Message> {
+    text_input(placeholder, inputs.get(field))
+        .on_input(move |input| Message::FilterChanged(field, input))
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+/// Label with a min and a max input next to each other
+fn bounds_row<'a>(
+    label: &'a str,
+    min: FilterField,
+    max: FilterField,
+    inputs: &'a FilterInputs,
+) -> Element<'a, Message> {
+    column![
+        text(label).size(14),
+        row![filter_input("Min", min, inputs), filter_input("Max", max, inputs)].spacing(10),
+    ]
+    .spacing(5)
+    .into()
+}
+
+/// Typed search filters, the key count only shows for mania
+pub fn filters_form(inputs: &FilterInputs, mania: bool) -> Element<'_, Message> {
+    use FilterField::*;
+
+    let mut form = column![
+        text("Ranked status:").size(14),
+        pick_list(STATUSES, Some(inputs.status), Message::StatusFilterSelected)
+            .width(Length::Fill)
+            .padding(10),
+        bounds_row("Star rating:", StarsMin, StarsMax, inputs),
+        bounds_row("Approach rate (AR):", ArMin, ArMax, inputs),
+        bounds_row("Overall difficulty (OD):", OdMin, OdMax, inputs),
+        bounds_row("Circle size (CS):", CsMin, CsMax, inputs),
+        bounds_row("HP drain (HP):", HpMin, HpMax, inputs),
+        bounds_row("BPM:", BpmMin, BpmMax, inputs),
+        bounds_row("Length in seconds:", LengthMin, LengthMax, inputs),
+    ]
+    .spacing(15);
+    if mania {
+        form = form.push(text("Key count:").size(14));
+        form = form.push(filter_input("Any", Keys, inputs));
+    }
+    form.push(text("Genre:").size(14))
+        .push(
+            pick_list(GENRES, Some(inputs.genre), Message::GenreSelected)
+                .width(Length::Fill)
+                .padding(10),
+        )
+        .push(text("Language:").size(14))
+        .push(
+            pick_list(LANGUAGES, Some(inputs.language), Message::LanguageSelected)
+                .width(Length::Fill)
+                .padding(10),
+        )
+        .push(text("Ranked between (YYYY, YYYY-MM or YYYY-MM-DD):").size(14))
+        .push(
+            row![
+                filter_input("From", RankedFrom, inputs),
+                filter_input("To", RankedTo, inputs),
+            ]
+            .spacing(10),
+        )
+        .push(checkbox("Has video", inputs.video).on_toggle(Message::VideoToggled))
+        .push(checkbox("Has storyboard", inputs.storyboard).on_toggle(Message::StoryboardToggled))
+        .into()
+}
+
+pub fn max_attempts_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Number of tries before giving up on a map", value)
+        .on_input(Message::MaxAttemptsChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+pub fn proxy_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("e.g. http://127.0.0.1:8080 or socks5://127.0.0.1:1080", value)
+        .on_input(Message::ProxyChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+pub fn global_limit_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Unlimited", value)
+        .on_input(Message::GlobalLimitChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+pub fn per_download_limit_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Unlimited", value)
+        .on_input(Message::PerDownloadLimitChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+pub fn concurrency_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Number of maps downloaded at once", value)
+        .on_input(Message::ConcurrencyChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
 pub fn game_mode_pick(selected: Option<GameModeOption>) -> Element<'static, Message> {
     pick_list(GameModeOption::ALL, selected, Message::GameModeSelected)
         .width(Length::Fill)
@@ -26,11 +170,257 @@ pub fn game_mode_pick(selected: Option<GameModeOption>) -> Element<'static, Mess
         .into()
 }
 
-pub fn download_button(is_downloading: bool) -> Element<'static, Message> {
+pub fn source_pick(selected: SourceOption) -> Element<'static, Message> {
+    pick_list(SourceOption::ALL, Some(selected), Message::SourceSelected)
+        .width(Length::Fill)
+        .padding(10)
+        .into()
+}
+
+/// One checkbox per list of `lists`
+pub fn user_lists(lists: &[UserListOption], ticked: &[UserListOption]) -> Element<'static, Message> {
+    lists
+        .iter()
+        .fold(column![text("Lists:").size(14)].spacing(10), |column, &list| {
+            column.push(
+                checkbox(list.to_string(), ticked.contains(&list))
+                    .on_toggle(move |enabled| Message::UserListToggled(list, enabled)),
+            )
+        })
+        .into()
+}
+
+pub fn pack_type_pick(selected: PackType) -> Element<'static, Message> {
+    pick_list(PackType::ALL, Some(selected), Message::PackTypeSelected)
+        .width(Length::Fill)
+        .padding(10)
+        .into()
+}
+
+pub fn pack_tags_input<'a>(value: &'a str) -> Element<'a, Message> {
+    text_input("Enter pack tags", value)
+        .on_input(Message::PackTagsChanged)
+        .padding(10)
+        .width(Length::Fill)
+        .into()
+}
+
+/// Listed packs with a button each to add them to the tags
+pub fn packs_browser<'a>(packs: &'a [PackInfo], has_more: bool, browsing: bool) -> Element<'a, Message> {
+    let browse = if browsing { None } else { Some(Message::BrowsePacks) };
+    let mut panel = column![button(text("Browse packs").size(14)).on_press_maybe(browse)].spacing(8);
+    for pack in packs {
+        panel = panel.push(
+            row![
+                text(format!("{} - {}", pack.tag, pack.name)).size(12).width(Length::Fill),
+                button(text("Add").size(12)).on_press(Message::PackPicked(pack.tag.clone())),
+            ]
+            .spacing(10)
+            .align_items(iced::Alignment::Center),
+        );
+    }
+    if has_more {
+        let more = if browsing { None } else { Some(Message::MorePacks) };
+        panel = panel.push(button(text("Load older packs").size(12)).on_press_maybe(more));
+    }
+    panel.into()
+}
+
+pub fn links_editor(content: &text_editor::Content) -> Element<'_, Message> {
+    text_editor(content)
+        .on_action(Message::LinksEdited)
+        .height(200)
+        .padding(10)
+        .into()
+}
+
+/// Path input to add the links of a text file
+pub fn links_file_import<'a>(path: &'a str) -> Element<'a, Message> {
+    row![
+        text_input("Import links from a file", path)
+            .on_input(Message::LinksFileChanged)
+            .on_submit(Message::ImportLinksFile)
+            .padding(10)
+            .width(Length::Fill),
+        button(text("Import")).on_press(Message::ImportLinksFile).padding(10),
+    ]
+    .spacing(10)
+    .into()
+}
+
+pub fn mirror_pick(selected: Option<MirrorOption>) -> Element<'static, Message> {
+    pick_list(MirrorOption::ALL, selected, Message::MirrorSelected)
+        .width(Length::Fill)
+        .padding(10)
+        .into()
+}
+
+pub fn mirror_capabilities(selected: Option<MirrorOption>) -> Element<'static, Message> {
+    let label = match selected {
+        Some(mirror) => format!("Serves: {}", mirror.to_source().capabilities()),
+        None => String::new(),
+    };
+
+    text(label).size(12).into()
+}
+
+pub fn fallback_checkbox(use_fallback: bool) -> Element<'static, Message> {
+    checkbox("Fall back to other mirrors when a map fails", use_fallback)
+        .on_toggle(Message::FallbackToggled)
+        .into()
+}
+
+pub fn package_pick(selected: Option<PackageVariant>) -> Element<'static, Message> {
+    pick_list(PackageVariant::ALL, selected, Message::PackageSelected)
+        .width(Length::Fill)
+        .padding(10)
+        .into()
+}
+
+pub fn verify_set_id_checkbox(verify_set_id: bool) -> Element<'static, Message> {
+    checkbox("Check that archives belong to the requested set", verify_set_id)
+        .on_toggle(Message::VerifySetIdToggled)
+        .into()
+}
+
+pub fn adaptive_concurrency_checkbox(adaptive: bool) -> Element<'static, Message> {
+    checkbox("Adapt to mirror load, using the number above as maximum", adaptive)
+        .on_toggle(Message::AdaptiveConcurrencyToggled)
+        .into()
+}
+
+pub fn resume_panel<'a>(unfinished: Option<&[QueueEntry]>, is_downloading: bool) -> Element<'a, Message> {
+    let Some(entries) = unfinished else {
+        return column![].into();
+    };
+
+    let bytes_done: u64 = entries.iter().map(|entry| entry.bytes_done).sum();
+    let label = format!(
+        "An earlier batch has {} maps left ({:.1} MiB already downloaded).",
+        entries.len(),
+        bytes_done as f64 / (1024.0 * 1024.0)
+    );
+    let mut resume = button(text("Resume")).padding(10);
+    let mut discard = button(text("Discard")).padding(10);
+    if !is_downloading {
+        resume = resume.on_press(Message::ResumeQueue);
+        discard = discard.on_press(Message::DiscardQueue);
+    }
+
+    column![text(label).size(14), row![resume, discard].spacing(10)]
+        .spacing(10)
+        .into()
+}
+
+/// Live search count, `counts` is `None` while no search is running
+pub fn search_panel<'a>(counts: Option<(usize, usize)>) -> Element<'a, Message> {
+    let Some((found, pages)) = counts else {
+        return column![].into();
+    };
+
+    row![
+        text(format!("Found {} sets on {} pages", found, pages))
+            .size(14)
+            .width(Length::Fill),
+        button(text("Stop search")).on_press(Message::StopSearch).padding(10),
+    ]
+    .spacing(10)
+    .align_items(iced::Alignment::Center)
+    .into()
+}
+
+/// Pause and cancel buttons, `paused` is `None` while no batch is running
+pub fn batch_controls<'a>(paused: Option<bool>) -> Element<'a, Message> {
+    let Some(paused) = paused else {
+        return column![].into();
+    };
+
+    let pause = if paused {
+        button(text("Resume")).on_press(Message::ResumeBatch)
+    } else {
+        button(text("Pause")).on_press(Message::PauseBatch)
+    };
+    let cancel = button(text("Cancel all")).on_press(Message::CancelBatch);
+
+    row![pause.padding(10), cancel.padding(10)].spacing(10).into()
+}
+
+/// Overall progress of the batch and a row for each map worth showing, maps can be cancelled while `can_cancel`
+pub fn progress_panel<'a>(progress: &BatchProgress, can_cancel: bool) -> Element<'a, Message> {
+    if progress.maps().is_empty() {
+        return column![].into();
+    }
+
+    let (done, total) = progress.counts();
+    let mut rows = column![].spacing(8);
+    for map in progress.shown() {
+        let (label, fraction) = match &map.state {
+            MapState::Queued => (format!("{} - queued", map.map_id), 0.0),
+            MapState::Downloading {
+                mirror,
+                filename,
+                downloaded,
+                total,
+            } => (
+                format!("{} ({})", filename, mirror),
+                *downloaded as f32 / (*total).max(1) as f32,
+            ),
+            MapState::Finished { mirror } => (format!("{} - served by {}", map.map_id, mirror), 1.0),
+            MapState::Failed(error) => (format!("{} - failed: {}", map.map_id, error), 0.0),
+            MapState::Skipped(reason) => (format!("{} - skipped, {}", map.map_id, reason), 0.0),
+        };
+        let mut line = row![text(label).size(12).width(Length::Fill)]
+            .spacing(10)
+            .align_items(iced::Alignment::Center);
+        if can_cancel && map.is_active() {
+            line = line.push(button(text("Cancel").size(12)).on_press(Message::CancelMap(map.map_id)));
+        }
+        rows = rows.push(line);
+        rows = rows.push(progress_bar(0.0..=1.0, fraction).height(Length::Fixed(6.0)));
+    }
+    column![
+        text(format!("Progress: {}/{} maps", done, total)).size(14),
+        progress_bar(0.0..=1.0, done as f32 / total.max(1) as f32).height(Length::Fixed(10.0)),
+        // Like the results, the rows scroll on their own instead of pushing the form out of view
+        scrollable(rows).height(PROGRESS_HEIGHT),
+    ]
+    .spacing(8)
+    .into()
+}
+
+pub fn search_button(is_busy: bool) -> Element<'static, Message> {
+    let mut search = button(
+        text("Search")
+            .horizontal_alignment(iced::alignment::Horizontal::Center)
+            .width(Length::Fill),
+    )
+    .padding(10)
+    .width(Length::Fill);
+    if !is_busy {
+        search = search.on_press(Message::StartSearch);
+    }
+    search.into()
+}
+
+pub fn download_links_button(is_busy: bool) -> Element<'static, Message> {
+    let mut download = button(
+        text("Download links")
+            .horizontal_alignment(iced::alignment::Horizontal::Center)
+            .width(Length::Fill),
+    )
+    .padding(10)
+    .width(Length::Fill);
+    if !is_busy {
+        download = download.on_press(Message::DownloadLinks);
+    }
+    download.into()
+}
+
+pub fn download_button(is_downloading: bool, selected: usize) -> Element<'static, Message> {
     let label = if is_downloading {
-        "Downloading..."
+        String::from("Downloading...")
     } else {
-        "Start download"
+        format!("Download {} selected", selected)
     };
 
     button(
@@ -44,6 +434,66 @@ pub fn download_button(is_downloading: bool) -> Element<'static, Message> {
     .into()
 }
 
+/// Found mapsets with a checkbox each, plus bulk selection buttons
+pub fn results_panel<'a>(results: &SearchResults) -> Element<'a, Message> {
+    if results.rows().is_empty() {
+        return column![].into();
+    }
+
+    let selected = results.rows().iter().filter(|row| row.selected).count();
+    let header = row![
+        text(format!("Results: {} of {} selected", selected, results.rows().len()))
+            .size(14)
+            .width(Length::Fill),
+        button(text("All").size(12)).on_press(Message::SelectAll),
+        button(text("None").size(12)).on_press(Message::SelectNone),
+        button(text("Invert").size(12)).on_press(Message::InvertSelection),
+    ]
+    .spacing(10)
+    .align_items(iced::Alignment::Center);
+
+    let mut list = column![].spacing(8);
+    for (index, row) in results.rows().iter().enumerate() {
+        let info = &row.info;
+        let mut details = format!("{} | by {} | {:?}", info.mapset_id, info.creator, info.status);
+        if let Some((min, max)) = info.stars {
+            details.push_str(&format!(" | {:.2}-{:.2}*", min, max));
+        }
+        if info.owned {
+            details.push_str(" | owned");
+        }
+        list = list.push(
+            column![
+                checkbox(format!("{} - {}", info.artist, info.title), row.selected)
+                    .on_toggle(move |selected| Message::ResultToggled(index, selected))
+                    .size(16)
+                    .text_size(14),
+                text(details).size(12),
+            ]
+            .spacing(2),
+        );
+    }
+    // Long result lists scroll on their own, so they don't push the rest of the form out of view
+    column![header, scrollable(list).height(RESULTS_HEIGHT)]
+        .spacing(8)
+        .into()
+}
+
+/// Export the selected results to a list file, or import one in their place
+pub fn list_file_panel<'a>(path: &'a str, selected: usize) -> Element<'a, Message> {
+    let export = (selected > 0).then_some(Message::ExportList);
+    row![
+        text_input("Path of a .csv or .json list", path)
+            .on_input(Message::ListFileChanged)
+            .padding(10)
+            .width(Length::Fill),
+        button(text(format!("Export {} selected", selected))).on_press_maybe(export).padding(10),
+        button(text("Import")).on_press(Message::ImportList).padding(10),
+    ]
+    .spacing(10)
+    .into()
+}
+
 pub fn status_text<'a>(status: &'a str) -> Element<'a, Message> {
     text(status)
         .size(16)
@@ -0,0 +1,210 @@
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use rosu_v2::prelude::{Genre, Language, RankStatus};
+
+use crate::backend::osu::{Bounds, SearchFilters};
+
+/// A value offered in a pick list, shown by its label
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Labeled<T> {
+    pub value: T,
+    label: &'static str,
+}
+
+impl<T> fmt::Display for Labeled<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+const fn labeled<T>(value: T, label: &'static str) -> Labeled<T> {
+    Labeled { value, label }
+}
+
+pub const STATUSES: &[Labeled<Option<RankStatus>>] = &[
+    labeled(None, "Any"),
+    labeled(Some(RankStatus::Ranked), "Ranked"),
+    labeled(Some(RankStatus::Qualified), "Qualified"),
+    labeled(Some(RankStatus::Loved), "Loved"),
+    labeled(Some(RankStatus::Pending), "Pending"),
+    labeled(Some(RankStatus::Graveyard), "Graveyard"),
+];
+
+pub const GENRES: &[Labeled<Option<Genre>>] = &[
+    labeled(None, "Any"),
+    labeled(Some(Genre::Unspecified), "Unspecified"),
+    labeled(Some(Genre::VideoGame), "Video Game"),
+    labeled(Some(Genre::Anime), "Anime"),
+    labeled(Some(Genre::Rock), "Rock"),
+    labeled(Some(Genre::Pop), "Pop"),
+    labeled(Some(Genre::Other), "Other"),
+    labeled(Some(Genre::Novelty), "Novelty"),
+    labeled(Some(Genre::HipHop), "Hip Hop"),
+    labeled(Some(Genre::Electronic), "Electronic"),
+    labeled(Some(Genre::Metal), "Metal"),
+    labeled(Some(Genre::Classical), "Classical"),
+    labeled(Some(Genre::Folk), "Folk"),
+    labeled(Some(Genre::Jazz), "Jazz"),
+];
+
+pub const LANGUAGES: &[Labeled<Option<Language>>] = &[
+    labeled(None, "Any"),
+    labeled(Some(Language::English), "English"),
+    labeled(Some(Language::Japanese), "Japanese"),
+    labeled(Some(Language::Chinese), "Chinese"),
+    labeled(Some(Language::Instrumental), "Instrumental"),
+    labeled(Some(Language::Korean), "Korean"),
+    labeled(Some(Language::French), "French"),
+    labeled(Some(Language::German), "German"),
+    labeled(Some(Language::Swedish), "Swedish"),
+    labeled(Some(Language::Spanish), "Spanish"),
+    labeled(Some(Language::Italian), "Italian"),
+    labeled(Some(Language::Russian), "Russian"),
+    labeled(Some(Language::Polish), "Polish"),
+    labeled(Some(Language::Other), "Other"),
+    labeled(Some(Language::Unspecified), "Unspecified"),
+];
+
+/// Text inputs of the search form that hold a filter value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FilterField {
+    StarsMin,
+    StarsMax,
+    ArMin,
+    ArMax,
+    OdMin,
+    OdMax,
+    CsMin,
+    CsMax,
+    HpMin,
+    HpMax,
+    BpmMin,
+    BpmMax,
+    LengthMin,
+    LengthMax,
+    Keys,
+    RankedFrom,
+    RankedTo,
+}
+
+/// State of the filter controls, turned into `SearchFilters` when a search starts
+#[derive(Debug, Clone)]
+pub struct FilterInputs {
+    pub status: Labeled<Option<RankStatus>>,
+    pub genre: Labeled<Option<Genre>>,
+    pub language: Labeled<Option<Language>>,
+    pub video: bool,
+    pub storyboard: bool,
+    values: HashMap<FilterField, String>,
+}
+
+impl Default for FilterInputs {
+    fn default() -> Self {
+        Self {
+            status: STATUSES[0],
+            genre: GENRES[0],
+            language: LANGUAGES[0],
+            video: false,
+            storyboard: false,
+            values: HashMap::new(),
+        }
+    }
+}
+
+impl FilterInputs {
+    pub fn get(&self, field: FilterField) -> &str {
+        self.values.get(&field).map_or("", String::as_str)
+    }
+
+    pub fn set(&mut self, field: FilterField, value: String) {
+        self.values.insert(field, value);
+    }
+
+    /// Parse the inputs, the key count only counts for mania
+    pub fn to_filters(&self, mania: bool) -> Result<SearchFilters, String> {
+        use FilterField::*;
+
+        Ok(SearchFilters {
+            status: self.status.value,
+            stars: self.bounds(StarsMin, StarsMax, "Star rating")?,
+            ar: self.bounds(ArMin, ArMax, "AR")?,
+            od: self.bounds(OdMin, OdMax, "OD")?,
+            cs: self.bounds(CsMin, CsMax, "CS")?,
+            hp: self.bounds(HpMin, HpMax, "HP")?,
+            bpm: self.bounds(BpmMin, BpmMax, "BPM")?,
+            length: self.bounds(LengthMin, LengthMax, "Length")?,
+            keys: if mania { self.number(Keys, "Key count")? } else { None },
+            genre: self.genre.value,
+            language: self.language.value,
+            video: self.video,
+            storyboard: self.storyboard,
+            ranked: Bounds {
+                min: self.date(RankedFrom)?,
+                max: self.date(RankedTo)?,
+            },
+        })
+    }
+
+    fn number<T: FromStr>(&self, field: FilterField, name: &str) -> Result<Option<T>, String> {
+        let input = self.get(field).trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        input
+            .parse()
+            .map(Some)
+            .map_err(|_| format!("Error: {} must be a number.", name))
+    }
+
+    fn bounds<T: FromStr>(&self, min: FilterField, max: FilterField, name: &str) -> Result<Bounds<T>, String> {
+        Ok(Bounds {
+            min: self.number(min, name)?,
+            max: self.number(max, name)?,
+        })
+    }
+
+    /// A ranked date bound, `YYYY`, `YYYY-MM` or `YYYY-MM-DD`
+    fn date(&self, field: FilterField) -> Result<Option<String>, String> {
+        let input = self.get(field).trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+        let parts: Vec<&str> = input.split('-').collect();
+        let valid = parts.len() <= 3
+            && parts[0].len() == 4
+            && parts.iter().all(|part| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit()));
+        if valid {
+            Ok(Some(input.to_string()))
+        } else {
+            Err(String::from("Error: Ranked dates must look like 2020, 2020-06 or 2020-06-15."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gui::filters::*;
+
+    #[test]
+    pub fn test_to_filters() {
+        let mut inputs = FilterInputs::default();
+        inputs.set(FilterField::StarsMin, " 4.5 ".to_string());
+        inputs.set(FilterField::Keys, "7".to_string());
+        inputs.set(FilterField::RankedFrom, "2020-06".to_string());
+        inputs.status = STATUSES[1];
+
+        let filters = inputs.to_filters(true).unwrap();
+        assert_eq!(filters.stars, Bounds { min: Some(4.5), max: None });
+        assert_eq!(filters.keys, Some(7));
+        assert_eq!(filters.status, Some(RankStatus::Ranked));
+        assert_eq!(filters.query(), "stars>=4.5 keys=7 ranked>=2020-06");
+        // The key count is ignored outside of mania
+        assert_eq!(inputs.to_filters(false).unwrap().keys, None);
+
+        inputs.set(FilterField::ArMax, "high".to_string());
+        assert!(inputs.to_filters(true).is_err());
+        inputs.set(FilterField::ArMax, String::new());
+        inputs.set(FilterField::RankedTo, "June 2020".to_string());
+        assert!(inputs.to_filters(true).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::backend::download::{ProgressEvent, SkipReason};
+
+/// Maps that reached an end which are still listed
+const RECENT_MAPS: usize = 20;
+/// Queued maps listed after the running ones
+const NEXT_MAPS: usize = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MapState {
+    Queued,
+    Downloading {
+        mirror: &'static str,
+        filename: String,
+        downloaded: u64,
+        total: u64,
+    },
+    Finished { mirror: &'static str },
+    Failed(String),
+    Skipped(SkipReason),
+}
+
+#[derive(Debug, Clone)]
+pub struct MapProgress {
+    pub map_id: u32,
+    pub state: MapState,
+}
+
+impl MapProgress {
+    /// Whether the map may still be cancelled
+    pub fn is_active(&self) -> bool {
+        matches!(self.state, MapState::Queued | MapState::Downloading { .. })
+    }
+}
+
+/// Progress of every map of a batch, in the order they were reported
+#[derive(Debug, Default)]
+pub struct BatchProgress {
+    maps: Vec<MapProgress>,
+    /// Position of each map in `maps`
+    index: HashMap<u32, usize>,
+    /// Positions of the maps that reached an end most recently, latest first
+    recent: VecDeque<usize>,
+}
+
+impl BatchProgress {
+    pub fn maps(&self) -> &[MapProgress] {
+        &self.maps
+    }
+
+    /// The maps worth a row of their own: running ones, the latest to end and the next in line
+    ///
+    /// Batches run into the thousands, listing every map would make the window crawl.
+    pub fn shown(&self) -> Vec<&MapProgress> {
+        let running = self.maps.iter().filter(|map| matches!(map.state, MapState::Downloading { .. }));
+        let recent = self.recent.iter().map(|&i| &self.maps[i]);
+        let next = self
+            .maps
+            .iter()
+            .filter(|map| map.state == MapState::Queued)
+            .take(NEXT_MAPS);
+        running.chain(recent).chain(next).collect()
+    }
+
+    /// Number of maps that reached an end, and the number of maps in the batch
+    pub fn counts(&self) -> (usize, usize) {
+        let done = self.maps.iter().filter(|map| !map.is_active()).count();
+        (done, self.maps.len())
+    }
+
+    pub fn apply(&mut self, event: ProgressEvent) {
+        let (map_id, state) = match event {
+            ProgressEvent::Queued { map_id } => (map_id, MapState::Queued),
+            ProgressEvent::Started {
+                map_id,
+                mirror,
+                filename,
+                downloaded,
+                total,
+            } => (
+                map_id,
+                MapState::Downloading {
+                    mirror,
+                    filename,
+                    downloaded,
+                    total,
+                },
+            ),
+            ProgressEvent::Bytes {
+                map_id,
+                downloaded: new,
+                ..
+            } => {
+                let map = self.index.get(&map_id).map(|&i| &mut self.maps[i]);
+                if let Some(MapProgress {
+                    state: MapState::Downloading { downloaded, .. },
+                    ..
+                }) = map
+                {
+                    *downloaded = new;
+                }
+                return;
+            }
+            ProgressEvent::Finished { map_id, mirror } => (map_id, MapState::Finished { mirror }),
+            ProgressEvent::Failed { map_id, error } => (map_id, MapState::Failed(error)),
+            ProgressEvent::Skipped { map_id, reason } => (map_id, MapState::Skipped(reason)),
+        };
+        let i = match self.index.get(&map_id) {
+            Some(&i) => i,
+            None => {
+                self.maps.push(MapProgress { map_id, state: MapState::Queued });
+                self.index.insert(map_id, self.maps.len() - 1);
+                self.maps.len() - 1
+            }
+        };
+        self.maps[i].state = state;
+        if !self.maps[i].is_active() {
+            self.recent.retain(|&recent| recent != i);
+            self.recent.push_front(i);
+            self.recent.truncate(RECENT_MAPS);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::gui::progress::*;
+
+    #[test]
+    pub fn test_apply_events() {
+        let mut progress = BatchProgress::default();
+        progress.apply(ProgressEvent::Skipped {
+            map_id: 1,
+            reason: SkipReason::AlreadyOwned,
+        });
+        progress.apply(ProgressEvent::Queued { map_id: 2 });
+        progress.apply(ProgressEvent::Started {
+            map_id: 2,
+            mirror: "Sayobot",
+            filename: "2 Artist - Title.osz".to_string(),
+            downloaded: 0,
+            total: 100,
+        });
+        progress.apply(ProgressEvent::Bytes {
+            map_id: 2,
+            downloaded: 40,
+            total: 100,
+        });
+        assert_eq!(progress.counts(), (1, 2));
+        assert!(matches!(
+            progress.maps()[1].state,
+            MapState::Downloading { downloaded: 40, .. }
+        ));
+
+        progress.apply(ProgressEvent::Finished {
+            map_id: 2,
+            mirror: "Sayobot",
+        });
+        assert_eq!(progress.counts(), (2, 2));
+        assert_eq!(progress.maps()[1].state, MapState::Finished { mirror: "Sayobot" });
+    }
+
+    #[test]
+    pub fn test_shown_maps_are_bounded() {
+        let mut progress = BatchProgress::default();
+        for map_id in 0..1000 {
+            progress.apply(ProgressEvent::Queued { map_id });
+        }
+        progress.apply(ProgressEvent::Started {
+            map_id: 500,
+            mirror: "Sayobot",
+            filename: "500.osz".to_string(),
+            downloaded: 0,
+            total: 100,
+        });
+        for map_id in 0..30 {
+            progress.apply(ProgressEvent::Finished { map_id, mirror: "Sayobot" });
+        }
+
+        let shown: Vec<u32> = progress.shown().iter().map(|map| map.map_id).collect();
+        assert_eq!(shown.len(), 1 + RECENT_MAPS + NEXT_MAPS);
+        assert_eq!(shown[0], 500);
+        assert_eq!(shown[1], 29);
+        assert_eq!(shown[1 + RECENT_MAPS], 30);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::backend::osu::MapsetInfo;
+
+#[derive(Debug, Clone)]
+pub struct ResultRow {
+    pub info: MapsetInfo,
+    pub selected: bool,
+}
+
+/// Mapsets of the last search and which of them are picked for download
+#[derive(Debug, Default)]
+pub struct SearchResults {
+    rows: Vec<ResultRow>,
+}
+
+impl SearchResults {
+    /// Everything that isn't owned yet starts out selected
+    pub fn new(mapsets: Vec<MapsetInfo>) -> Self {
+        let rows = mapsets
+            .into_iter()
+            .map(|info| ResultRow {
+                selected: !info.owned,
+                info,
+            })
+            .collect();
+        Self { rows }
+    }
+
+    pub fn rows(&self) -> &[ResultRow] {
+        &self.rows
+    }
+
+    pub fn set_selected(&mut self, index: usize, selected: bool) {
+        if let Some(row) = self.rows.get_mut(index) {
+            row.selected = selected;
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.rows.iter_mut().for_each(|row| row.selected = true);
+    }
+
+    pub fn select_none(&mut self) {
+        self.rows.iter_mut().for_each(|row| row.selected = false);
+    }
+
+    pub fn invert(&mut self) {
+        self.rows.iter_mut().for_each(|row| row.selected = !row.selected);
+    }
+
+    pub fn selected_ids(&self) -> Vec<u32> {
+        self.rows
+            .iter()
+            .filter(|row| row.selected)
+            .map(|row| row.info.mapset_id)
+            .collect()
+    }
+
+    pub fn selected_mapsets(&self) -> Vec<MapsetInfo> {
+        self.rows
+            .iter()
+            .filter(|row| row.selected)
+            .map(|row| row.info.clone())
+            .collect()
+    }
+
+    pub fn owned_count(&self) -> usize {
+        self.rows.iter().filter(|row| row.info.owned).count()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::backend::test_util;
+    use crate::gui::results::*;
+
+    fn mapset(mapset_id: u32, owned: bool) -> MapsetInfo {
+        MapsetInfo {
+            owned,
+            ..test_util::mapset(mapset_id)
+        }
+    }
+
+    #[test]
+    pub fn test_selection() {
+        let mut results = SearchResults::new(vec![mapset(1, false), mapset(2, true), mapset(3, false)]);
+        assert_eq!(results.selected_ids(), vec![1, 3]);
+        assert_eq!(results.owned_count(), 1);
+
+        results.invert();
+        assert_eq!(results.selected_ids(), vec![2]);
+        assert_eq!(results.selected_mapsets()[0].mapset_id, 2);
+        results.set_selected(0, true);
+        assert_eq!(results.selected_ids(), vec![1, 2]);
+        results.select_none();
+        assert!(results.selected_ids().is_empty());
+        results.select_all();
+        assert_eq!(results.selected_ids(), vec![1, 2, 3]);
+    }
+}